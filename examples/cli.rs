@@ -6,21 +6,29 @@ To run from keyring-search
 `--target` search by target
 `--user` search by user
 `--service` search by service
-Defaults to target if no commands are passed
+Defaults to target if no commands are passed, prompting for a
+query that is matched against the whole target ignoring case,
+with `*` and `?` as wildcards, a blank line prompts again
 Optional subcommands
 `limit` [integer] restrict search to return specified amount of results
 `all` unrestricted amount of search results
 Defaults to all
+`--config` [path] load the search's backend and options from a
+JSON profile, requires the `serde` feature
+Exits with 0 when credentials are found, otherwise the
+error's exit code: 1 no results, 2 search error,
+3 invalid query, 4 unexpected, 5 store unavailable
 */
 extern crate keyring_search;
 
 use clap::Parser;
-use keyring_search::{Limit, List, Search};
-use std::io::{self, Write};
+use keyring_search::search::GlobMatcher;
+use keyring_search::{Error, Limit, List, Search};
+use std::io::{self, BufRead, Write};
+use std::process;
 
 fn main() {
     let args: Cli = Cli::parse();
-    let list: String;
 
     let limit = match args.limit {
         Some(Command::All) => Limit::All,
@@ -28,32 +36,78 @@ fn main() {
         None => Limit::All,
     };
 
-    let search = match Search::new() {
-        Ok(search) => search,
-        Err(err) => panic!("Error creating search: {}", err.to_string()),
+    #[cfg(feature = "serde")]
+    let search = match &args.config {
+        Some(path) => load_config(path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(err.exit_code());
+        }),
+        None => Search::default(),
     };
+    #[cfg(not(feature = "serde"))]
+    let search = Search::default();
 
-    if let Some(query) = args.service {
-        let result = search.by_service(&query);
-        list = List::list_credentials(&result, limit);
+    let list = if let Some(query) = args.service {
+        search.search_and_list("service", &query, limit)
     } else if let Some(query) = args.target {
-        let result = search.by_target(&query);
-        list = List::list_credentials(&result, limit);
+        search.search_and_list("target", &query, limit)
     } else if let Some(query) = args.user {
-        let result = search.by_user(&query);
-        list = List::list_credentials(&result, limit);
+        search.search_and_list("user", &query, limit)
     } else {
-        print!("Search defaulted to `by_target`, enter query: ");
-        let mut arg = String::new();
-        io::stdout().flush().expect("Failed to flush stdout");
+        // A typed query is a glob rather than a regex, so characters
+        // such as `(` are taken literally.
+        let query = prompt_query().unwrap_or_else(|| {
+            let err = Error::InvalidQuery("no query entered".to_string());
+            eprintln!("\n{err}");
+            process::exit(err.exit_code());
+        });
+        GlobMatcher::new(&query)
+            .and_then(|glob| search.by_matcher("target", &glob))
+            .map(|result| List::list_credentials(&Ok(result), limit))
+    };
+
+    match list {
+        Ok(list) => println!("{list}"),
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(err.exit_code());
+        }
+    }
+}
 
-        io::stdin().read_line(&mut arg).expect("Invalid input arg");
+/// Prompts for a query until a non-blank line is entered, or
+/// returns `None` at the end of input. Input that isn't valid
+/// UTF-8 is decoded lossily rather than failing.
+fn prompt_query() -> Option<String> {
+    let stdin = io::stdin();
+    loop {
+        print!("Search defaulted to `by_target`, enter query (`*` and `?` are wildcards): ");
+        io::stdout().flush().expect("Failed to flush stdout");
 
-        let result = search.by_target(&arg.trim());
-        list = List::list_credentials(&result, limit);
+        let mut line = Vec::new();
+        let read = stdin
+            .lock()
+            .read_until(b'\n', &mut line)
+            .expect("Failed to read query");
+        if read == 0 {
+            return None;
+        }
+        let query = String::from_utf8_lossy(&line).trim().to_string();
+        if !query.is_empty() {
+            return Some(query);
+        }
     }
+}
 
-    println!("{list}")
+/// Builds the search saved in a JSON profile, such as
+/// `{ "backend": "mock", "match_mode": "IgnoreCase" }`.
+#[cfg(feature = "serde")]
+fn load_config(path: &str) -> keyring_search::Result<Search> {
+    let unreadable =
+        |err: String| keyring_search::Error::Unexpected(format!("config {path}: {err}"));
+    let profile = std::fs::read_to_string(path).map_err(|err| unreadable(err.to_string()))?;
+    let config = serde_json::from_str(&profile).map_err(|err| unreadable(err.to_string()))?;
+    Search::from_config(config)
 }
 
 /// Keyring-search CLI:
@@ -70,6 +124,10 @@ pub struct Cli {
     #[clap(short, long, value_parser)]
     /// Search store by service
     pub service: Option<String>,
+    #[cfg(feature = "serde")]
+    #[clap(short, long, value_parser)]
+    /// Load the search's backend and options from a JSON profile
+    pub config: Option<String>,
     #[clap(subcommand)]
     /// Specify amount of credentials returned from search
     pub limit: Option<Command>,
@@ -80,5 +138,5 @@ pub enum Command {
     /// Return all results from store
     All,
     /// Return specified amount of results
-    Limit { amount: i64 },
+    Limit { amount: usize },
 }
@@ -4,10 +4,37 @@ pub enum Error {
     SearchError(String),
     Unexpected(String),
     NoResults,
+    InvalidQuery(String),
+    /// The credential store couldn't be reached, such as when the
+    /// secret-service daemon isn't running. Unlike a
+    /// [SearchError](Error::SearchError), the query isn't at fault.
+    Connection(String),
+    /// A [native query](crate::Search::native_query) named a parameter
+    /// the backend can't map to its store.
+    UnsupportedField(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// Maps the error to a process exit code, so scripts can branch on the outcome.
+    ///
+    /// [NoResults](Error::NoResults) is 1, [SearchError](Error::SearchError) is 2,
+    /// [InvalidQuery](Error::InvalidQuery) is 3, [Unexpected](Error::Unexpected) is 4,
+    /// [Connection](Error::Connection) is 5 and [UnsupportedField](Error::UnsupportedField) is 6.
+    /// Success is left to the caller as 0.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoResults => 1,
+            Error::SearchError(_) => 2,
+            Error::InvalidQuery(_) => 3,
+            Error::Unexpected(_) => 4,
+            Error::Connection(_) => 5,
+            Error::UnsupportedField(_) => 6,
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -20,6 +47,15 @@ impl std::fmt::Display for Error {
             Error::NoResults => {
                 write!(f, "Search returned no results")
             }
+            Error::InvalidQuery(reason) => {
+                write!(f, "Invalid search query: {}", reason)
+            }
+            Error::Connection(reason) => {
+                write!(f, "Credential store unavailable: {}", reason)
+            }
+            Error::UnsupportedField(field) => {
+                write!(f, "Field not supported by this platform: {}", field)
+            }
         }
     }
 }
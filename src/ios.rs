@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 
-use security_framework::item::{ItemClass, ItemSearchOptions, Limit};
+use security_framework::item::{self, ItemClass, ItemSearchOptions};
 
 use super::error::{Error as ErrorCode, Result};
-use super::search::{CredentialSearch, CredentialSearchApi, CredentialSearchResult};
+use super::search::{
+    canonical_field, CredentialSearch, CredentialSearchApi, CredentialSearchResult,
+    CredentialSearchResultWithWarnings, IdFormat, Limit, MatchMode, SearchOptions, SearchPlan,
+    ValueMatch,
+};
 
 pub struct IosCredentialSearch {}
 
@@ -18,7 +22,50 @@ pub fn default_credential_search() -> Box<CredentialSearch> {
 
 impl CredentialSearchApi for IosCredentialSearch {
     fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
-        search(by, query)
+        search(by, query, &SearchOptions::default())
+    }
+
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        search(by, query, options).map(|results| CredentialSearchResultWithWarnings {
+            results,
+            ..Default::default()
+        })
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "ios"
+    }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        let attribute = match canonical_field("ios", by) {
+            Some(attribute) => attribute,
+            None => return format!("cannot search by {}, expected service or user", by),
+        };
+
+        let mut explanation = match options.match_mode {
+            MatchMode::Exact => format!(
+                "queries the app's keychain for generic passwords whose {} is \"{}\", ignoring case",
+                attribute, query
+            ),
+            _ => format!(
+                "fetches every generic password in the app's keychain, keeping those whose {} {}",
+                attribute,
+                ValueMatch::describe(query, options)
+            ),
+        };
+        if let Limit::Max(max) = options.limit {
+            explanation.push_str(&format!(", keeping at most {}", max));
+        }
+        explanation
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        SearchPlan::new(self, by, query, options, "the app's keychain")
     }
 }
 
@@ -29,12 +76,22 @@ enum IosSearchType {
 }
 
 // Perform search, can throw a SearchError, returns a CredentialSearchResult.
-fn search(by: &str, query: &str) -> CredentialSearchResult {
+//
+// Generic passwords can only be matched exactly, so for the Contains and
+// Regex match modes every item is fetched and filtered client-side, and
+// a maximum count is only pushed into an exact query.
+fn search(by: &str, query: &str, options: &SearchOptions) -> CredentialSearchResult {
     let mut new_search = ItemSearchOptions::new();
 
+    let limit = match (options.match_mode, options.limit) {
+        (MatchMode::Exact, Limit::Max(max)) => {
+            item::Limit::Max(i64::try_from(max).unwrap_or(i64::MAX))
+        }
+        _ => item::Limit::All,
+    };
     let search_default = &mut new_search
         .class(ItemClass::generic_password())
-        .limit(Limit::All)
+        .limit(limit)
         .load_attributes(true)
         .case_insensitive(Some(true));
 
@@ -49,36 +106,64 @@ fn search(by: &str, query: &str) -> CredentialSearchResult {
         _ => return Err(ErrorCode::Unexpected("by parameter iOS".to_string())),
     };
 
-    let search = match by {
-        IosSearchType::Service => search_default.service(query).search(),
-        IosSearchType::Account => search_default.account(query).search(),
+    let field = match by {
+        IosSearchType::Service => "svce",
+        IosSearchType::Account => "acct",
     };
+    // Only an exact query sets the attribute, the other match modes
+    // fetch every item and filter them below.
+    let value_match = match options.match_mode {
+        MatchMode::Exact => {
+            match by {
+                IosSearchType::Service => search_default.service(query),
+                IosSearchType::Account => search_default.account(query),
+            };
+            None
+        }
+        _ => Some(ValueMatch::new(query, options)?),
+    };
+    let search = search_default.search();
 
     let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
 
     let results = match search {
         Ok(items) => items,
-        Err(err) => {
-            println!("Error while searching, {}", err.to_string());
-            return Err(ErrorCode::SearchError(err.to_string()));
-        }
+        Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
     };
 
-    for item in results {
-        match to_credential_search_result(item.simplify_dict(), &mut outer_map) {
+    let items = results
+        .iter()
+        .map(|item| item.simplify_dict())
+        .filter(|item| match (&value_match, item) {
+            (Some(value_match), Some(map)) => map
+                .get(field)
+                .map_or(false, |value| value_match.is_match(value)),
+            _ => true,
+        })
+        .take(options.limit.max_items());
+
+    for (count, item) in items.enumerate() {
+        match to_credential_search_result(item, &mut outer_map, options.id_format, count + 1) {
             Ok(_) => {}
             Err(err) => return Err(err),
         }
     }
 
+    // The modes filtered above may have kept none of the items.
+    if outer_map.is_empty() {
+        return Err(ErrorCode::NoResults);
+    }
+
     Ok(outer_map)
 }
 // The returned item from search is converted to CredentialSearchResult type.
 // If none, a SearchError is returned for no items found. The outer map's key
-// is created with "user"@"service" to differentiate between credentials in the search.
+// is created from "user"@"service", or the result count, depending on the IdFormat.
 fn to_credential_search_result(
     item: Option<HashMap<String, String>>,
     outer_map: &mut HashMap<String, HashMap<String, String>>,
+    id_format: IdFormat,
+    count: usize,
 ) -> Result<()> {
     let result = match item {
         None => return Err(ErrorCode::NoResults),
@@ -96,7 +181,66 @@ fn to_credential_search_result(
 
     let label = format!("{acct}@{svce}");
 
-    outer_map.insert(format!("Label: {}", label), result);
+    let id = match id_format {
+        IdFormat::Numeric => count.to_string(),
+        IdFormat::UserAtService => format!("Label: {}", label),
+        IdFormat::LabelOnly => label,
+    };
+
+    outer_map.insert(id, result);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use security_framework::passwords::{delete_generic_password, set_generic_password};
+
+    use super::{to_credential_search_result, IdFormat};
+    use crate::{tests::generate_random_string, MatchMode, Search};
+
+    fn item() -> Option<HashMap<String, String>> {
+        let mut item = HashMap::new();
+        item.insert("acct".to_string(), "test-user".to_string());
+        item.insert("svce".to_string(), "test-service".to_string());
+        Some(item)
+    }
+
+    #[test]
+    fn test_id_format() {
+        let expected = [
+            (IdFormat::Numeric, "3"),
+            (IdFormat::UserAtService, "Label: test-user@test-service"),
+            (IdFormat::LabelOnly, "test-user@test-service"),
+        ];
+
+        for (id_format, id) in expected {
+            let mut outer_map = HashMap::new();
+            to_credential_search_result(item(), &mut outer_map, id_format, 3)
+                .expect("Failed to convert item");
+            assert!(outer_map.contains_key(id), "Missing id {}", id);
+        }
+    }
+
+    #[test]
+    fn test_contains_matches_longer_service() {
+        let name = generate_random_string();
+        let service = format!("{}-service", name);
+        set_generic_password(&service, "test-user", b"test-password")
+            .expect("Failed to create test credential");
+
+        let result = Search::new()
+            .expect("Error creating test search")
+            .match_mode(MatchMode::Contains)
+            .by_service(&name);
+        delete_generic_password(&service, "test-user").expect("Failed to delete test credential");
+
+        let result = result.expect("Contains should match the longer service");
+        assert_eq!(1, result.len());
+        assert!(result
+            .values()
+            .all(|fields| fields.get("svce") == Some(&service)));
+    }
+}
@@ -1,8 +1,15 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
-use super::error::Error as ErrorCode;
-use super::search::{CredentialSearch, CredentialSearchApi, CredentialSearchResult};
-use linux_keyutils::{KeyRing, KeyRingIdentifier, KeyType, Permission};
+use super::error::{Error as ErrorCode, Result};
+use super::search::{
+    BackendHealth, CredentialSearch, CredentialSearchApi, CredentialSearchResult,
+    CredentialSearchResultWithWarnings, Limit, SearchOptions, SearchPlan, ValueMatch,
+};
+use linux_keyutils::{Key, KeyRing, KeyRingIdentifier, KeyType, LinkNode, Permission};
+
+// The most links read from a single keyring when enumerating it.
+const MAX_LINKS: usize = 4096;
 
 pub struct KeyutilsCredentialSearch {}
 
@@ -20,15 +27,218 @@ impl CredentialSearchApi for KeyutilsCredentialSearch {
     /// If more control over the keyring is needed, call the
     /// (search_by_keyring) function manually.
     fn by(&self, _by: &str, query: &str) -> CredentialSearchResult {
-        search_by_keyring("session", query)
+        search_by_keyring("session", query, Limit::All)
+    }
+
+    fn by_with_options(
+        &self,
+        _by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        search_session(ValueMatch::new(query, options)?, options)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "keyutils"
+    }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        let mut explanation = format!(
+            "lists the keys in the session keyring, and the keyrings nested in it, \
+            whose description {}, ignoring the {} field",
+            ValueMatch::describe(query, options),
+            by
+        );
+        if options.owned_only {
+            explanation.push_str(", keeping the keys owned by the current user");
+        }
+        if let Limit::Max(max) = options.limit {
+            explanation.push_str(&format!(", stopping after {}", max));
+        }
+        explanation
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        let scope = "the session keyring and the keyrings nested in it";
+        SearchPlan::new(self, by, query, options, scope)
+    }
+
+    fn all(&self, options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        // Every description contains the empty string.
+        search_session(ValueMatch::Contains(String::new()), options)
     }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        Ok(
+            match KeyRing::from_special_id(KeyRingIdentifier::Session, false) {
+                Ok(_) => BackendHealth::new(self.backend_name(), None),
+                Err(err) => BackendHealth::new(self.backend_name(), Some(err.to_string())),
+            },
+        )
+    }
+}
+// Searches the session keyring for the keys whose description matches,
+// honoring the options' limit, owner and deadline.
+fn search_session(
+    value_match: ValueMatch,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    let owner = match options.owned_only {
+        true => Some(current_uid()),
+        false => None,
+    };
+
+    let mut walk = KeyringWalk::new(value_match, options.limit, owner, options.deadline);
+    let truncated = walk.collect(get_keyring("session")?)?;
+    if walk.results.is_empty() && !truncated {
+        return Err(ErrorCode::NoResults);
+    }
+
+    // The kernel's own search returns one key, so several matches
+    // are flagged for callers relying on a single result.
+    Ok(CredentialSearchResultWithWarnings {
+        ambiguous: walk.results.len() > 1,
+        results: walk.results,
+        warnings: walk.warnings,
+        truncated,
+    })
 }
 /// Search for credential items in the specified keyring.
 ///
 /// To utilize search of any keyring, call this function
 /// directly. The generic platform independent search
 /// defaults to the `session` keyring.
-pub fn search_by_keyring(by: &str, query: &str) -> CredentialSearchResult {
+///
+/// Returns the keys whose description is the query, in the keyring
+/// and in the keyrings nested in it, as the kernel's own search
+/// would find them. [Max](Limit::Max) stops after that many matches,
+/// other limits return them all.
+pub fn search_by_keyring(by: &str, query: &str, limit: Limit) -> CredentialSearchResult {
+    search_keyring_matching(by, ValueMatch::Exact(query.to_string()), limit)
+}
+/// Search for every credential item in the specified keyring
+/// whose description contains the query, ignoring case.
+///
+/// Unlike [search_by_keyring], which returns the keys whose
+/// description is the query, this returns every key whose
+/// description contains it, in the keyring and in the keyrings
+/// nested in it. [Max](Limit::Max) stops after that many matches,
+/// other limits return them all.
+pub fn search_keyring(by: &str, query: &str, limit: Limit) -> CredentialSearchResult {
+    search_keyring_matching(by, ValueMatch::Contains(query.to_lowercase()), limit)
+}
+// Searches the keyring for the keys whose description matches.
+fn search_keyring_matching(
+    by: &str,
+    value_match: ValueMatch,
+    limit: Limit,
+) -> CredentialSearchResult {
+    let mut walk = KeyringWalk::new(value_match, limit, None, None);
+    walk.collect(get_keyring(by)?)?;
+
+    if walk.results.is_empty() {
+        return Err(ErrorCode::NoResults);
+    }
+
+    Ok(walk.results)
+}
+// A search through a keyring and the keyrings nested in it, keeping the
+// keys whose description matches, and that the owner owns if given,
+// until there are `max` matches.
+struct KeyringWalk {
+    value_match: ValueMatch,
+    owner: Option<u32>,
+    deadline: Option<Instant>,
+    max: usize,
+    visited: Vec<KeyRing>,
+    results: HashMap<String, HashMap<String, String>>,
+    warnings: Vec<String>,
+}
+
+impl KeyringWalk {
+    fn new(
+        value_match: ValueMatch,
+        limit: Limit,
+        owner: Option<u32>,
+        deadline: Option<Instant>,
+    ) -> KeyringWalk {
+        KeyringWalk {
+            value_match,
+            owner,
+            deadline,
+            max: limit.max_items(),
+            visited: Vec::new(),
+            results: HashMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    // Adds the matching keys linked in the ring, descending into nested
+    // keyrings. Keys and nested keyrings that can't be read are left out
+    // with a warning. Returns whether the deadline passed before a nested
+    // keyring was searched.
+    fn collect(&mut self, ring: KeyRing) -> Result<bool> {
+        self.visited.push(ring);
+        let links = match ring.get_links(MAX_LINKS) {
+            Ok(links) => links,
+            Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
+        };
+
+        for link in links.iter() {
+            if self.results.len() >= self.max {
+                break;
+            }
+            match link {
+                LinkNode::Key(key) => {
+                    let metadata = match key.metadata() {
+                        Ok(metadata) => metadata,
+                        Err(err) => {
+                            self.warnings
+                                .push(format!("Skipped key {}: {}", key.get_id().0, err));
+                            continue;
+                        }
+                    };
+                    if self
+                        .owner
+                        .map_or(false, |owner| metadata.get_uid() != owner)
+                    {
+                        continue;
+                    }
+                    if self.value_match.is_match(metadata.get_description()) {
+                        self.results
+                            .insert(key.get_id().0.to_string(), key_metadata(*key)?);
+                    }
+                }
+                LinkNode::KeyRing(nested) => {
+                    if self
+                        .deadline
+                        .map_or(false, |deadline| Instant::now() >= deadline)
+                    {
+                        return Ok(true);
+                    }
+                    if !self.visited.contains(nested) {
+                        match self.collect(*nested) {
+                            Ok(true) => return Ok(true),
+                            Ok(false) => {}
+                            Err(err) => self
+                                .warnings
+                                .push(format!("Skipped a nested keyring: {}", err)),
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(false)
+    }
+}
+// The effective user ID, which the kernel checks key ownership against.
+fn current_uid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+// Resolves a keyring from its name.
+fn get_keyring(by: &str) -> Result<KeyRing> {
     let by = match by {
         "thread" => KeyRingIdentifier::Thread,
         "process" => KeyRingIdentifier::Process,
@@ -39,20 +249,15 @@ pub fn search_by_keyring(by: &str, query: &str) -> CredentialSearchResult {
         _ => return Err(ErrorCode::SearchError("must match keyutils keyring identifiers: thread, process, session, user, user session, group".to_string())),
     };
 
-    let ring = match KeyRing::from_special_id(by, false) {
-        Ok(ring) => ring,
-        Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
-    };
-
-    let result = match ring.search(query) {
-        Ok(result) => result,
-        Err(err) => match err {
-            linux_keyutils::KeyError::KeyDoesNotExist => return Err(ErrorCode::NoResults),
-            _ => return Err(ErrorCode::SearchError(err.to_string())),
-        },
-    };
-
-    let result_data = match result.metadata() {
+    match KeyRing::from_special_id(by, false) {
+        Ok(ring) => Ok(ring),
+        Err(err) => Err(ErrorCode::SearchError(err.to_string())),
+    }
+}
+// Builds the result's inner map from the key's metadata, with its ID,
+// so callers don't depend on the outer key, and its payload length.
+fn key_metadata(key: Key) -> Result<HashMap<String, String>> {
+    let result_data = match key.metadata() {
         Ok(data) => data,
         Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
     };
@@ -63,7 +268,6 @@ pub fn search_by_keyring(by: &str, query: &str) -> CredentialSearchResult {
 
     let permission_string = get_permission_chars(permission_bits[0]);
 
-    let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
     let mut inner_map: HashMap<String, String> = HashMap::new();
 
     inner_map.insert("perm".to_string(), permission_string);
@@ -74,8 +278,49 @@ pub fn search_by_keyring(by: &str, query: &str) -> CredentialSearchResult {
         "description".to_string(),
         result_data.get_description().to_string(),
     );
+    inner_map.insert("id".to_string(), key.get_id().0.to_string());
+    // Reading into an empty buffer returns the payload's length without
+    // copying the secret. Keys the caller can't read are left without it.
+    if let Ok(length) = key.read(&mut [0u8; 0]) {
+        inner_map.insert("payload_length".to_string(), length.to_string());
+    }
 
-    outer_map.insert(result.get_id().0.to_string(), inner_map);
+    Ok(inner_map)
+}
+/// Search for credential items across every keyutils keyring.
+///
+/// Keys with the same description can be present in several
+/// keyrings, so each keyring is searched in turn. Outer keys
+/// are tagged with the source keyring, `"{keyring}: {id}"`,
+/// and the inner map gains a `keyring` field. Keyrings that
+/// don't exist or can't be searched are skipped.
+pub fn search_all_keyrings(query: &str) -> CredentialSearchResult {
+    let keyrings = [
+        "thread",
+        "process",
+        "session",
+        "user",
+        "user session",
+        "group",
+    ];
+
+    let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+    for keyring in keyrings {
+        let result = match search_by_keyring(keyring, query, Limit::All) {
+            Ok(result) => result,
+            Err(_) => continue,
+        };
+
+        for (id, mut inner_map) in result {
+            inner_map.insert("keyring".to_string(), keyring.to_string());
+            outer_map.insert(format!("{}: {}", keyring, id), inner_map);
+        }
+    }
+
+    if outer_map.is_empty() {
+        return Err(ErrorCode::NoResults);
+    }
 
     Ok(outer_map)
 }
@@ -117,8 +362,13 @@ fn get_permission_chars(permission_data: u8) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{get_key_type, get_permission_chars, KeyRing, KeyRingIdentifier};
-    use crate::{tests::generate_random_string, Error, Limit, List, Search};
+    use super::{
+        get_key_type, get_permission_chars, search_all_keyrings, KeyRing, KeyRingIdentifier,
+    };
+    use crate::{
+        set_default_credential_search, tests::generate_random_string, Error, Limit, List,
+        MatchMode, Search,
+    };
     use keyring::{credential::CredentialApi, keyutils::KeyutilsCredential};
     use std::collections::HashSet;
 
@@ -156,12 +406,14 @@ mod tests {
             .as_str(),
         );
         expected.push_str(format!("ktype: {}\n", get_key_type(metadata.get_type())).as_str());
+        expected.push_str(format!("id: {}\n", credential.get_id().0).as_str());
+        expected.push_str(format!("payload_length: {}\n", password.len()).as_str());
+        expected.push_str(format!("service: {}\nuser: {}\ntarget: \n", name, name).as_str());
 
         let query = format!("keyring-rs:{}@{}", name, name);
-        let result = Search {
-            inner: Box::new(super::KeyutilsCredentialSearch {}),
-        }
-        .by_user(&query);
+        let result = set_default_credential_search(Box::new(super::KeyutilsCredentialSearch {}))
+            .expect("Failed to create keyutils search")
+            .by_user(&query);
         let list = List::list_credentials(&result, Limit::All);
 
         let expected_set: HashSet<&str> = expected.lines().collect();
@@ -172,6 +424,97 @@ mod tests {
             .expect("Couldn't delete test-search-by-user");
     }
 
+    #[test]
+    fn test_max_result() {
+        let prefix = generate_random_string();
+        let entries: Vec<KeyutilsCredential> = (0..3)
+            .map(|i| {
+                let name = format!("{}{}", prefix, i);
+                let entry = KeyutilsCredential::new_with_target(None, &name, &name)
+                    .expect("Failed to create searchable entry");
+                entry
+                    .set_password("max result test password")
+                    .expect("Failed to set password");
+                entry
+            })
+            .collect();
+
+        let result = set_default_credential_search(Box::new(super::KeyutilsCredentialSearch {}))
+            .expect("Failed to create keyutils search")
+            .with_limit(Limit::Max(2))
+            .match_mode(MatchMode::Contains)
+            .by_user(&prefix)
+            .expect("Failed to search with limit");
+
+        for entry in entries {
+            entry
+                .delete_password()
+                .expect("Couldn't delete test-max-result");
+        }
+        assert_eq!(2, result.len());
+    }
+
+    #[test]
+    fn test_owned_only() {
+        let name = generate_random_string();
+        let entry = KeyutilsCredential::new_with_target(None, &name, &name)
+            .expect("Failed to create searchable entry");
+        entry
+            .set_password("owned only test password")
+            .expect("Failed to set password");
+
+        let result = set_default_credential_search(Box::new(super::KeyutilsCredentialSearch {}))
+            .expect("Failed to create keyutils search")
+            .owned_only(true)
+            .match_mode(MatchMode::Contains)
+            .by_user(&name)
+            .expect("Failed to search owned keys");
+        let uid = super::current_uid();
+
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-owned-only");
+        assert_eq!(1, result.len());
+        assert!(result
+            .values()
+            .all(|inner| inner.get("uid") == Some(&uid.to_string())));
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let name = generate_random_string();
+        let first = KeyutilsCredential::new_with_target(None, &format!("{}-first", name), &name)
+            .expect("Failed to create searchable entry");
+        let second = KeyutilsCredential::new_with_target(None, &format!("{}-second", name), &name)
+            .expect("Failed to create searchable entry");
+        first
+            .set_password("ambiguous test password")
+            .expect("Failed to set password");
+        second
+            .set_password("ambiguous test password")
+            .expect("Failed to set password");
+
+        let search = set_default_credential_search(Box::new(super::KeyutilsCredentialSearch {}))
+            .expect("Failed to create keyutils search")
+            .match_mode(MatchMode::Contains);
+        let both = search
+            .by_verbose("user", &name)
+            .expect("Failed to search both keys");
+        let one = search
+            .by_verbose("user", &format!("{}-first", name))
+            .expect("Failed to search one key");
+
+        first
+            .delete_password()
+            .expect("Couldn't delete test-ambiguous");
+        second
+            .delete_password()
+            .expect("Couldn't delete test-ambiguous");
+        assert_eq!(2, both.results.len());
+        assert!(both.ambiguous);
+        assert!(!one.ambiguous);
+    }
+
     #[test]
     fn test_no_results() {
         let name = generate_random_string();
@@ -181,4 +524,23 @@ mod tests {
 
         assert!(matches!(search.unwrap_err(), Error::NoResults));
     }
+
+    #[test]
+    fn test_search_all_keyrings() {
+        let name = generate_random_string();
+        let entry = keyring::keyutils::KeyutilsCredential::new_with_target(None, &name, &name)
+            .expect("Failed to create searchable entry");
+        entry
+            .set_password("search all test password")
+            .expect("Failed to set password");
+
+        let query = format!("keyring-rs:{}@{}", name, name);
+        let result = search_all_keyrings(&query).expect("Failed to search all keyrings");
+
+        assert!(result.iter().any(|(id, inner)| id.starts_with("session: ")
+            && inner.get("keyring").map(String::as_str) == Some("session")));
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-all-keyrings");
+    }
 }
@@ -16,10 +16,21 @@ This crate, originally planned as a feature for
 the platform specific keystores based on user provided search parameters.
  */
 
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 pub use error::{Error, Result};
-pub use search::{CredentialSearch, CredentialSearchResult, Limit};
+#[cfg(feature = "serde")]
+pub use search::SearchConfig;
+pub use search::{
+    BackendHealth, CredentialSearch, CredentialSearchResult, CredentialSearchResultExt,
+    CredentialSearchResultWithWarnings, FromSearchResult, IdFormat, ItemClassKind, Keychain, Limit,
+    MatchMode, MatchTier, Matcher, RegexFlags, SearchEntry, SearchField, SearchOptions, SearchPlan,
+    SearchResult, SearchResults, SearchTiming, SearchTree,
+};
 // Included keystore implementations and default choice thereof.
 
 pub mod mock;
@@ -96,37 +107,1328 @@ use mock as default;
 )))]
 use mock as default;
 
+// Whether the default is the mock store on a platform that has a
+// store of its own, because the features for it are off.
+const MOCK_FALLBACK: bool = cfg!(any(
+    all(
+        target_os = "linux",
+        not(feature = "secret-service"),
+        not(feature = "linux-keyutils")
+    ),
+    all(target_os = "freebsd", not(feature = "secret-service")),
+    all(target_os = "openbsd", not(feature = "secret-service")),
+    all(target_os = "macos", not(feature = "platform-macos")),
+    all(target_os = "windows", not(feature = "platform-windows")),
+    all(target_os = "ios", not(feature = "platform-ios")),
+));
+
+#[cfg(feature = "strict-platform")]
+const _: () = assert!(
+    !MOCK_FALLBACK,
+    "strict-platform is enabled but no store for this platform is, \
+     so the default search would be the mock store: enable the platform's feature"
+);
+
 pub mod error;
 pub mod search;
 
 pub fn set_default_credential_search(default_search: Box<CredentialSearch>) -> Result<Search> {
     Ok(Search {
-        inner: default_search,
+        inner: Arc::from(default_search),
+        options: SearchOptions::default(),
+        observer: None,
     })
 }
 
-fn default_credential_search() -> Result<Search> {
-    let credentials = default::default_credential_search();
-    Ok(Search { inner: credentials })
-}
+fn default_credential_search() -> Result<Search> {
+    Ok(Search::default())
+}
+
+/// A configured credential search.
+///
+/// Cloning is cheap, the backend and observer are shared,
+/// so one search can be handed to several threads.
+///
+/// Every result carries the [baseline](search::BASELINE_FIELDS) `service`,
+/// `user` and `target` fields alongside the backend's own, empty when the
+/// backend doesn't know them, except for [by_raw](Search::by_raw) searches.
+#[derive(Clone)]
+pub struct Search {
+    inner: Arc<CredentialSearch>,
+    options: SearchOptions,
+    observer: Option<Arc<dyn Fn(SearchTiming) + Send + Sync>>,
+}
+
+/// Create a new instance of the Credential Search with the platform default.
+///
+/// Building the platform default never fails, backends such as
+/// secret-service only connect once a search is performed, so
+/// this is an infallible alternative to [new](Search::new).
+impl Default for Search {
+    fn default() -> Search {
+        Search {
+            inner: Arc::from(default::default_credential_search()),
+            options: SearchOptions::default(),
+            observer: None,
+        }
+    }
+}
+/// Debug output names the backend in use rather than the boxed search.
+impl std::fmt::Debug for Search {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Search")
+            .field("backend", &self.backend_name())
+            .field("options", &self.options)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+/// The implementation of the Search structures methods.
+///
+/// The default search types are: Target, User, and Service.
+/// On linux-keyutils these all default to searching the 'session'
+/// keyring for keys whose description is the query. If searching
+/// in a different keyring, utilize the platform specific `search_keyring`
+/// function
+impl Search {
+    /// Create a new instance of the Credential Search.
+    ///
+    /// The default credential search is used.
+    pub fn new() -> Result<Search> {
+        default_credential_search()
+    }
+    /// Create a search from a saved [SearchConfig].
+    ///
+    /// The backend is `mock` or the platform default, named either
+    /// `default` or by its [backend_name](Search::backend_name). Any
+    /// other backend returns [Unexpected](Error::Unexpected), as custom
+    /// backends have to be set with [set_default_credential_search].
+    #[cfg(feature = "serde")]
+    pub fn from_config(config: SearchConfig) -> Result<Search> {
+        let search = match config.backend.as_deref() {
+            Some("mock") => set_default_credential_search(mock::default_credential_search())?,
+            None | Some("default") => Search::new()?,
+            Some(name) if name == Search::default().backend_name() => Search::new()?,
+            Some(name) => return Err(Error::Unexpected(format!("unknown backend {}", name))),
+        };
+        Ok(Search {
+            options: config.options,
+            ..search
+        })
+    }
+    /// The backend and options of this search, to save and later
+    /// restore with [from_config](Search::from_config).
+    #[cfg(feature = "serde")]
+    pub fn config(&self) -> SearchConfig {
+        SearchConfig {
+            backend: Some(self.backend_name().to_string()),
+            options: self.options.clone(),
+        }
+    }
+    /// Specifies how the ID of each result is built.
+    ///
+    /// Only iOS honors this, as the other platforms already
+    /// number their results. Defaults to [UserAtService](IdFormat::UserAtService).
+    pub fn id_format(mut self, id_format: IdFormat) -> Search {
+        self.options.id_format = id_format;
+        self
+    }
+    /// Specifies whether locked collections are skipped.
+    ///
+    /// Only secret-service honors this. When true, locked collections
+    /// are left out of the search instead of failing it, and each one
+    /// skipped is reported as a warning. Defaults to false.
+    pub fn skip_locked(mut self, skip_locked: bool) -> Search {
+        self.options.skip_locked = skip_locked;
+        self
+    }
+    /// Searches over an existing Secret service connection.
+    ///
+    /// Instead of opening its own connection for each search, the
+    /// search uses the one given, avoiding a redundant D-Bus connection
+    /// and sharing its session. Replaces the backend, keeping the options.
+    #[cfg(any(
+        all(
+            target_os = "linux",
+            feature = "secret-service",
+            not(feature = "linux-no-secret-service")
+        ),
+        all(target_os = "freebsd", feature = "secret-service"),
+        all(target_os = "openbsd", feature = "secret-service"),
+    ))]
+    pub fn with_secret_service(
+        mut self,
+        ss: ::secret_service::blocking::SecretService<'static>,
+    ) -> Search {
+        self.inner = Arc::from(secret_service::connected_credential_search(ss));
+        self
+    }
+    /// Specifies whether collections are searched concurrently.
+    ///
+    /// Only secret-service honors this. Each collection is a blocking
+    /// D-Bus round trip, so with many collections, searching each on its
+    /// own thread overlaps the waits. The threads share the one connection
+    /// rather than each opening its own, so the cost is a thread spawned per
+    /// collection, which outweighs the gain with only a few collections.
+    /// Results are numbered in collection order either way. Defaults to false.
+    pub fn parallel_collections(mut self, parallel_collections: bool) -> Search {
+        self.options.parallel_collections = parallel_collections;
+        self
+    }
+    /// Keeps the items created after the given time.
+    ///
+    /// Only secret-service honors this. Every result carries the item's
+    /// `created` and `modified` times, in seconds since the Unix epoch,
+    /// and the items matching the query are kept by those times, to the
+    /// second. Items whose times can't be read are skipped and reported
+    /// as warnings. Defaults to any time.
+    pub fn ss_created_after(mut self, time: SystemTime) -> Search {
+        self.options.created_after = Some(time);
+        self
+    }
+    /// Keeps the items last modified before the given time.
+    ///
+    /// Only secret-service honors this, as for
+    /// [ss_created_after](Search::ss_created_after), such as to find
+    /// credentials no one has touched in a year. Defaults to any time.
+    pub fn ss_modified_before(mut self, time: SystemTime) -> Search {
+        self.options.modified_before = Some(time);
+        self
+    }
+    /// Specifies how the query is matched against values.
+    ///
+    /// On secret-service and iOS, [Exact](MatchMode::Exact) uses the
+    /// native search, which is case-sensitive. [IgnoreCase](MatchMode::IgnoreCase),
+    /// [Contains](MatchMode::Contains), [Regex](MatchMode::Regex),
+    /// [Tokens](MatchMode::Tokens), [Prefix](MatchMode::Prefix) and [Suffix](MatchMode::Suffix)
+    /// list every item and filter client-side, which costs a DBus call per
+    /// item on secret-service, and fetches every generic password on iOS.
+    /// keyutils lists every key and matches its description in any mode.
+    /// Windows and the mock backend honor Tokens, Prefix and Suffix, and
+    /// match every other mode as a regex, see [regex_flags](Search::regex_flags).
+    /// macOS ignores this and always uses its native search.
+    /// Defaults to [Exact](MatchMode::Exact).
+    pub fn match_mode(mut self, match_mode: MatchMode) -> Search {
+        self.options.match_mode = match_mode;
+        self
+    }
+    /// Caps the number of items the search fetches.
+    ///
+    /// A [Max](Limit::Max) limit is enforced by the backend, so the
+    /// search stops early rather than [List] trimming the output.
+    /// macOS and iOS pass it to the keychain query so attributes are
+    /// loaded for only that many items, which is much faster on large
+    /// keychains, although a filtered query fetches every item. Windows
+    /// stops building results, and secret-service stops searching
+    /// collections, once there are that many. linux-keyutils stops
+    /// enumerating after that many matches. Other limits fetch everything.
+    /// Searches that list every credential and filter it, such as
+    /// [by_matcher](Search::by_matcher), apply the limit to the matches,
+    /// and those summing up the store, such as
+    /// [field_cardinality](Search::field_cardinality), ignore it.
+    /// Defaults to [All](Limit::All).
+    pub fn with_limit(mut self, limit: Limit) -> Search {
+        self.options.limit = limit;
+        self
+    }
+    /// Specifies whether only credentials the current user owns are searched.
+    ///
+    /// On multi-user systems some of the credentials listed belong to
+    /// other contexts. linux-keyutils only keeps keys owned by the
+    /// current user's effective ID. Windows leaves out enterprise
+    /// credentials, which roam with a domain profile, keeping those
+    /// persisted for the session or the local machine. secret-service
+    /// skips locked collections, as with [skip_locked](Search::skip_locked).
+    /// macOS and iOS keychains already belong to the user and ignore this.
+    /// Defaults to false.
+    pub fn owned_only(mut self, owned_only: bool) -> Search {
+        self.options.owned_only = owned_only;
+        self
+    }
+    /// Specifies how long enumerating the credentials may take.
+    ///
+    /// Only Windows honors this. On domain-joined machines `CredEnumerateW`
+    /// can stall on domain credentials, so it is run on its own thread and
+    /// the search fails with a [SearchError](Error::SearchError) once the
+    /// timeout passes. The enumeration can't be cancelled, so its thread
+    /// is left to finish in the background. Defaults to no timeout.
+    pub fn enumeration_timeout(mut self, timeout: Duration) -> Search {
+        self.options.enumeration_timeout = Some(timeout);
+        self
+    }
+    /// Specifies when the search stops searching further.
+    ///
+    /// Only linux-keyutils and secret-service honor this. The deadline is
+    /// checked before each nested keyring or collection is searched, so a
+    /// search over many of them returns what it found so far rather than
+    /// running on. [by_verbose](Search::by_verbose) marks such results as
+    /// `truncated`, and they may be empty. Collections searched in
+    /// [parallel](Search::parallel_collections) all start at once, and are
+    /// always searched in full. Defaults to no deadline.
+    pub fn deadline(mut self, deadline: Instant) -> Search {
+        self.options.deadline = Some(deadline);
+        self
+    }
+    /// Specifies whether invalid UTF-16 skips a credential.
+    ///
+    /// Only Windows honors this. By default invalid sequences, such as
+    /// lone surrogates, are replaced with U+FFFD, which can make an
+    /// exact name miss. When true, such credentials are left out of the
+    /// search and each one skipped is reported as a warning. Defaults to false.
+    pub fn strict_utf16(mut self, strict_utf16: bool) -> Search {
+        self.options.strict_utf16 = strict_utf16;
+        self
+    }
+    /// Specifies whether each credential's blob is read, so it can be
+    /// searched with `by("blob", query)` and is returned as `Blob`.
+    ///
+    /// Only Windows honors this. Some apps keep the identifier worth
+    /// searching, such as an email, in the `CredentialBlob` rather than
+    /// the target or user. The blob is often the password itself, and
+    /// may be binary: it is decoded as UTF-16, as keyring-rs writes it,
+    /// and as base64 when it isn't text. Defaults to false.
+    ///
+    /// **Warning:** the results then hold secrets, so take care not to
+    /// log, list or export them.
+    pub fn include_blob(mut self, include_blob: bool) -> Search {
+        self.options.include_blob = include_blob;
+        self
+    }
+    /// Specifies the credential types searched.
+    ///
+    /// Only Windows honors this, taking `CRED_TYPE` values such as
+    /// `CRED_TYPE_GENERIC` or `CRED_TYPE_DOMAIN_PASSWORD`. Credentials
+    /// of other types are left out before matching. Defaults to all types.
+    ///
+    /// # Example
+    ///     // CRED_TYPE_DOMAIN_PASSWORD
+    ///     let search = keyring_search::Search::default().cred_types(&[2]);
+    pub fn cred_types(mut self, cred_types: &[u32]) -> Search {
+        self.options.cred_types = Some(cred_types.to_vec());
+        self
+    }
+    /// Specifies how regex queries are compiled.
+    ///
+    /// Windows and the mock backend match every query as a regex, as do
+    /// secret-service and iOS with the [Regex](MatchMode::Regex) match mode.
+    /// This can turn off case-insensitivity, or let `^`, `$` and `.`
+    /// work across the lines of a multi-line field such as a Windows comment.
+    /// Defaults to [RegexFlags::default], case-insensitive and Unicode-aware.
+    ///
+    /// # Example
+    ///     use keyring_search::{RegexFlags, Search};
+    ///     let search = Search::default().regex_flags(RegexFlags {
+    ///         case_insensitive: false,
+    ///         ..RegexFlags::default()
+    ///     });
+    pub fn regex_flags(mut self, regex_flags: RegexFlags) -> Search {
+        self.options.regex_flags = regex_flags;
+        self
+    }
+    /// Specifies whether a search that finds nothing succeeds.
+    ///
+    /// When true, a search that finds nothing returns an empty map,
+    /// rather than failing with [NoResults](Error::NoResults), on every
+    /// platform. Other errors are still returned. Defaults to false.
+    pub fn empty_as_ok(mut self, empty_as_ok: bool) -> Search {
+        self.options.empty_as_ok = empty_as_ok;
+        self
+    }
+    /// Specifies the class of keychain item searched.
+    ///
+    /// Only macOS honors this, searching generic passwords by default.
+    /// [Certificate](ItemClassKind::Certificate), [Key](ItemClassKind::Key)
+    /// and [Identity](ItemClassKind::Identity) items can only be searched
+    /// [by target](Search::by_target), their label. Setting a
+    /// [protocol](Search::with_protocol) or authentication type searches
+    /// internet passwords in place of generic ones.
+    ///
+    /// # Example
+    ///     use keyring_search::{ItemClassKind, Search};
+    ///     let search = Search::default().item_class(ItemClassKind::Certificate);
+    pub fn item_class(mut self, item_class: ItemClassKind) -> Search {
+        self.options.item_class = item_class;
+        self
+    }
+    /// Specifies the keychain searched.
+    ///
+    /// Only macOS honors this. By default every keychain in the user's
+    /// search list is searched, this scopes the search to one, such as
+    /// the [System](Keychain::System) keychain or a keychain file. A
+    /// keychain that can't be opened fails the search with a
+    /// [SearchError](Error::SearchError).
+    ///
+    /// # Example
+    ///     use keyring_search::{Keychain, Search};
+    ///     let search = Search::default().in_keychain(Keychain::System);
+    pub fn in_keychain(mut self, keychain: Keychain) -> Search {
+        self.options.keychain = Some(keychain);
+        self
+    }
+    /// Searches internet passwords for this protocol.
+    ///
+    /// Only macOS honors this. Setting a protocol, or an
+    /// [authentication type](Search::with_auth_type), searches internet
+    /// passwords rather than generic ones, keeping those whose `ptcl`
+    /// attribute matches. This tells an HTTPS credential from an FTP one
+    /// for the same server. Takes a name such as `https`, `ftp` or `ssh`,
+    /// or the four character code itself. The match is made after the
+    /// query, so a [Max](Limit::Max) limit is applied to the matches.
+    pub fn with_protocol(mut self, protocol: &str) -> Search {
+        self.options.protocol = Some(protocol.to_string());
+        self
+    }
+    /// Specifies whether every attribute is converted in full.
+    ///
+    /// Only macOS honors this. By default attributes are read as the
+    /// security-framework crate simplifies them, where numbers and
+    /// booleans are `unknown`, data is decoded as lossy UTF-8 and dates
+    /// use Core Foundation's description. When true, numbers and booleans
+    /// are written out, data is base64 and dates are ISO 8601 in UTC, to
+    /// diagnose an item with an unusual attribute. Internal attributes are
+    /// still hidden, see [hide_fields](Search::hide_fields). Defaults to false.
+    pub fn full_attributes(mut self, full_attributes: bool) -> Search {
+        self.options.full_attributes = full_attributes;
+        self
+    }
+    /// Searches internet passwords with this authentication type.
+    ///
+    /// Only macOS honors this, keeping internet passwords whose `atyp`
+    /// attribute matches, as [with_protocol](Search::with_protocol) does.
+    /// Takes a name such as `html_form`, `http_basic` or `ntlm`, or the
+    /// four character code itself.
+    pub fn with_auth_type(mut self, auth_type: &str) -> Search {
+        self.options.auth_type = Some(auth_type.to_string());
+        self
+    }
+    /// Specifies whether results are scored by relevance to the query.
+    ///
+    /// Each result gets a `score` field, see [relevance](search::relevance),
+    /// so an exact match ranks above a prefix, a substring, then a fuzzy
+    /// match. The score is kept even when [selecting](Search::select) fields.
+    /// List the results in that order with [list_ranked](List::list_ranked).
+    /// Defaults to false.
+    pub fn ranked(mut self, ranked: bool) -> Search {
+        self.options.ranked = ranked;
+        self
+    }
+    /// Specifies whether each result records the query it matched.
+    ///
+    /// Each result gets a `matched_query` field, see
+    /// [MATCHED_QUERY_FIELD](search::MATCHED_QUERY_FIELD), holding the
+    /// query as passed to the search. This tells which query found what
+    /// when several are combined, as by [by_any_of](Search::by_any_of),
+    /// where a credential matching several queries is then listed once
+    /// for each. The field is kept even when [selecting](Search::select)
+    /// fields. Defaults to false.
+    pub fn tag_matched_query(mut self, tag_matched_query: bool) -> Search {
+        self.options.tag_matched_query = tag_matched_query;
+        self
+    }
+    /// Specifies the tiers [by_smart](Search::by_smart) escalates
+    /// through, in order.
+    ///
+    /// Defaults to [DEFAULT_MATCH_TIERS](search::DEFAULT_MATCH_TIERS),
+    /// exact, then substring, then fuzzy.
+    pub fn match_tiers(mut self, tiers: &[MatchTier]) -> Search {
+        self.options.match_tiers = Some(tiers.to_vec());
+        self
+    }
+    /// Specifies the metadata fields kept in each result.
+    ///
+    /// Fields are matched case-insensitively against the keys the
+    /// backend returns, so `user` keeps Windows' `User`. Fields a
+    /// credential doesn't have are simply absent from its result.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::default().select(&["user", "service"]);
+    pub fn select(mut self, fields: &[&str]) -> Search {
+        self.options.select = Some(fields.iter().map(|field| field.to_string()).collect());
+        self
+    }
+    /// Specifies the metadata fields left out of each result.
+    ///
+    /// By default the internal fields in [DEFAULT_HIDDEN_FIELDS](search::DEFAULT_HIDDEN_FIELDS),
+    /// such as macOS' `pdmn` and `agrp`, are left out, so a listing
+    /// doesn't dump them to a terminal that might be shared. This
+    /// replaces that list, pass an empty list to keep every field.
+    /// Fields are matched case-insensitively. A field asked for by
+    /// [select](Search::select) is kept whether or not it's hidden.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::default().hide_fields(&[]);
+    pub fn hide_fields(mut self, fields: &[&str]) -> Search {
+        self.options.hide_fields = Some(fields.iter().map(|field| field.to_string()).collect());
+        self
+    }
+    /// Reports how long each search took to the given observer.
+    ///
+    /// The observer is called with a [SearchTiming] after every search,
+    /// successful or not. Nothing is timed when no observer is set.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::default()
+    ///         .with_observer(|timing| eprintln!("{} took {:?}", timing.backend, timing.duration));
+    pub fn with_observer(
+        mut self,
+        observer: impl Fn(SearchTiming) + Send + Sync + 'static,
+    ) -> Search {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+    /// Returns the name of the backend this search uses.
+    pub fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+    /// Whether this search uses the mock store because the features for
+    /// the platform's own store are off, such as a Linux build with
+    /// neither secret-service nor keyutils.
+    ///
+    /// The mock store starts out empty, so apps can warn rather than
+    /// report that nothing was found. Enable the `strict-platform`
+    /// feature to make such a build fail to compile instead.
+    pub fn is_mock_fallback(&self) -> bool {
+        MOCK_FALLBACK && self.backend_name() == "mock"
+    }
+    /// Checks whether the platform's store can be reached, without searching it,
+    /// so apps can tell an unavailable store from one with nothing in it.
+    ///
+    /// An unreachable store is reported in the [BackendHealth], not as an error.
+    /// On secret-service it reports how many collections there are and which
+    /// are locked, on linux-keyutils whether the session keyring is accessible,
+    /// on Windows whether its credentials can be enumerated and on macOS
+    /// whether the default keychain is reachable and unlocked.
+    pub fn health_check(&self) -> Result<BackendHealth> {
+        self.inner.health_check()
+    }
+    /// Returns the names of every backend compiled into this build.
+    ///
+    /// Unlike [backend_name](Search::backend_name), which is the backend
+    /// in use, this lists all that are available, e.g. both `keyutils`
+    /// and `secret-service` on Linux. The mock backend is always present.
+    pub fn backends_available() -> Vec<&'static str> {
+        #[allow(unused_mut)]
+        let mut backends = vec!["mock"];
+        #[cfg(all(target_os = "linux", feature = "linux-keyutils"))]
+        backends.push("keyutils");
+        #[cfg(any(
+            all(
+                target_os = "linux",
+                feature = "secret-service",
+                not(feature = "linux-no-secret-service")
+            ),
+            all(target_os = "freebsd", feature = "secret-service"),
+            all(target_os = "openbsd", feature = "secret-service"),
+        ))]
+        backends.push("secret-service");
+        #[cfg(all(target_os = "macos", feature = "platform-macos"))]
+        backends.push("macos");
+        #[cfg(all(target_os = "ios", feature = "platform-ios"))]
+        backends.push("ios");
+        #[cfg(all(target_os = "windows", feature = "platform-windows"))]
+        backends.push("windows");
+        backends
+    }
+    /// Specifies the parameter to search by and the query string.
+    ///
+    /// The parameter is passed on to the backend as is, so besides
+    /// `target`, `user` and `service`, it can name a platform's own
+    /// field, such as `label` or `account` on macOS. Every search goes
+    /// through here, so the options set on the search are passed on.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by("user", "Mr. Foo Bar");
+    pub fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
+        self.by_verbose(by, query).map(|result| result.results)
+    }
+    /// Specifies the [field](SearchField) to search by and the query string.
+    ///
+    /// The field is translated to the name the backend searches it by,
+    /// so `Account` searches the user on every platform. A field the
+    /// backend doesn't have, such as a label on Windows, fails with an
+    /// [UnsupportedField](Error::UnsupportedField). Use [by](Search::by)
+    /// for a platform's other fields, such as secret-service's `path`.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    /// [UnsupportedField](Error::UnsupportedField)
+    ///
+    /// # Example
+    ///     use keyring_search::SearchField;
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_field(SearchField::Account, "Mr. Foo Bar");
+    pub fn by_field(&self, field: SearchField, query: &str) -> CredentialSearchResult {
+        match field.by_name(self.backend_name()) {
+            Some(by) => self.by(by, query),
+            None => Err(Error::UnsupportedField(field.to_string())),
+        }
+    }
+    /// Specifies searching by target and the query string
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_target("Foo.app");
+    pub fn by_target(&self, query: &str) -> CredentialSearchResult {
+        self.by("target", query)
+    }
+    /// Specifies searching by user and the query string
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_user("Mr. Foo Bar");
+    pub fn by_user(&self, query: &str) -> CredentialSearchResult {
+        self.by("user", query)
+    }
+    /// Specifies searching by service and the query string
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_service("Bar inc.");
+    pub fn by_service(&self, query: &str) -> CredentialSearchResult {
+        self.by("service", query)
+    }
+    /// Specifies searching by label and the query string
+    ///
+    /// Only secret-service supports searching by label, matching
+    /// the label exactly. Every item is listed and filtered
+    /// client-side, so this is slower than the other searches.
+    /// Other platforms reject `label` as a search parameter, apart
+    /// from linux-keyutils which always searches the session keyring.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_label("Password for Foo");
+    pub fn by_label(&self, query: &str) -> CredentialSearchResult {
+        self.by("label", query)
+    }
+    /// Specifies searching by the item's DBus object path
+    ///
+    /// Only secret-service supports this, returning the single item at
+    /// the path, such as `/org/freedesktop/secrets/collection/login/1`,
+    /// as found in the `path` field of every secret-service result.
+    /// Only the collection holding the path is listed. Other platforms
+    /// reject `path` as a search parameter, apart from linux-keyutils
+    /// which always searches the session keyring.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_path("/org/freedesktop/secrets/collection/login/1");
+    pub fn by_path(&self, path: &str) -> CredentialSearchResult {
+        self.by("path", path)
+    }
+    /// Specifies searching by schema and the query string
+    ///
+    /// Only secret-service supports this, matching the `xdg:schema`
+    /// attribute apps tag their items with, such as
+    /// `org.freedesktop.Secret.Generic`. The attribute, otherwise left
+    /// out of results, is kept to confirm the match. Other platforms
+    /// reject `schema` as a search parameter, apart from linux-keyutils
+    /// which always searches the session keyring.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_schema("org.freedesktop.Secret.Generic");
+    pub fn by_schema(&self, query: &str) -> CredentialSearchResult {
+        self.by("schema", query)
+    }
+    /// Specifies searching every attribute value by the query string
+    ///
+    /// Only secret-service supports this, for when the attribute
+    /// keys aren't known. Every item is listed and any item with
+    /// a value matching the query, per the [match mode](Search::match_mode),
+    /// is returned with all of its attributes. Other platforms reject
+    /// `any` as a search parameter, apart from linux-keyutils which
+    /// always searches the session keyring.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_any_attribute_value("Foo");
+    pub fn by_any_attribute_value(&self, query: &str) -> CredentialSearchResult {
+        self.by("any", query)
+    }
+    /// Returns the first credential matching the query, if any.
+    ///
+    /// Results are ordered by ID, numerically where the backend numbers
+    /// them, so this is the credential `"1"` on most platforms. No match
+    /// is `Ok(None)`, only a failed search is an error.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(Some(result)) = search.first("user", "Mr. Foo Bar") {
+    ///         println!("{:?}", result.get("service"));
+    ///     }
+    pub fn first(&self, by: &str, query: &str) -> Result<Option<SearchResult<'static>>> {
+        let result = match self.by(by, query) {
+            Ok(result) => result,
+            Err(Error::NoResults) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        Ok(search::results(&result)
+            .into_iter()
+            .next()
+            .map(SearchResult::into_owned))
+    }
+    /// Searches by the given parameter and query string, grouping
+    /// the results by the value of another field.
+    ///
+    /// Fields are looked up as by [SearchResult::get], so `service`
+    /// groups by macOS' `svce`, or the target on Windows. Credentials without
+    /// the field are grouped under the empty string. Each group is ordered by ID.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(groups) = search.group_by("user", "Mr. Foo Bar", "service") {
+    ///         for (service, results) in groups {
+    ///             println!("{}: {}", service, results.len());
+    ///         }
+    ///     }
+    pub fn group_by(
+        &self,
+        by: &str,
+        query: &str,
+        group_field: &str,
+    ) -> Result<HashMap<String, Vec<SearchResult<'static>>>> {
+        let result = self.by(by, query)?;
+
+        let mut groups: HashMap<String, Vec<SearchResult<'static>>> = HashMap::new();
+        for search_result in search::results(&result) {
+            let group = search_result
+                .get(group_field)
+                .unwrap_or_default()
+                .to_string();
+            groups
+                .entry(group)
+                .or_default()
+                .push(search_result.into_owned());
+        }
+
+        Ok(groups)
+    }
+    /// Searches by the given parameter and query string, grouping the
+    /// results by service, then by user, such as for a tree view.
+    ///
+    /// The service is found as for [distinct_services](Search::distinct_services),
+    /// the target on Windows, and the user as by [SearchResult::get].
+    /// Credentials without one are grouped under the empty string,
+    /// as are Windows' `NO USER` credentials. Services and users are
+    /// sorted, and each user's results are ordered by ID.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(tree) = search.tree("user", "Mr. Foo Bar") {
+    ///         for (service, users) in tree {
+    ///             println!("{}", service);
+    ///             for (user, results) in users {
+    ///                 println!("  {}: {}", user, results.len());
+    ///             }
+    ///         }
+    ///     }
+    pub fn tree(&self, by: &str, query: &str) -> Result<SearchTree> {
+        let result = self.by(by, query)?;
+        let service_field = self.service_field();
+
+        let mut tree = SearchTree::new();
+        for search_result in search::results(&result) {
+            let service = search_result
+                .get(service_field)
+                .unwrap_or_default()
+                .to_string();
+            let user = match search_result.get("user") {
+                Some(search::NO_USER) | None => String::new(),
+                Some(user) => user.to_string(),
+            };
+            tree.entry(service)
+                .or_default()
+                .entry(user)
+                .or_default()
+                .push(search_result.into_owned());
+        }
+
+        Ok(tree)
+    }
+    /// Searches by the given parameter and query string, returning the
+    /// results in the order the comparator gives.
+    ///
+    /// The comparator sees each pair of results, so it can sort on any
+    /// field, or on several, such as by service then by user. Results
+    /// it considers equal keep their order by ID.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_sorted_by("user", "Mr. Foo Bar", |a, b| {
+    ///         a.get("service")
+    ///             .cmp(&b.get("service"))
+    ///             .then_with(|| a.get("user").cmp(&b.get("user")))
+    ///     });
+    pub fn by_sorted_by(
+        &self,
+        by: &str,
+        query: &str,
+        mut cmp: impl FnMut(&SearchResult, &SearchResult) -> Ordering,
+    ) -> Result<Vec<SearchResult<'static>>> {
+        let result = self.by(by, query)?;
+
+        let mut results: Vec<SearchResult<'static>> = search::results(&result)
+            .into_iter()
+            .map(SearchResult::into_owned)
+            .collect();
+        results.sort_by(|a, b| cmp(a, b));
+
+        Ok(results)
+    }
+    /// Searches by the given parameter and query string, building a value
+    /// of your own type from each result, ordered by ID.
+    ///
+    /// See [FromSearchResult]. For types that implement `Deserialize`,
+    /// use `by_deserialize`, with the `serde` feature.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected), when a result can't be converted
+    ///
+    /// # Example
+    ///     use keyring_search::{FromSearchResult, Result, SearchResult};
+    ///     struct User(String);
+    ///     impl FromSearchResult for User {
+    ///         fn from_search_result(result: &SearchResult) -> Result<User> {
+    ///             Ok(User(result.get("user").unwrap_or_default().to_string()))
+    ///         }
+    ///     }
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let users: Result<Vec<User>> = search.by_into("service", "Bar inc.");
+    pub fn by_into<T: FromSearchResult>(&self, by: &str, query: &str) -> Result<Vec<T>> {
+        let result = self.by(by, query)?;
+
+        search::results(&result)
+            .iter()
+            .map(T::from_search_result)
+            .collect()
+    }
+    /// Searches by the given parameter and query string, deserializing
+    /// each result, ordered by ID, into a type of your own.
+    ///
+    /// Fields are read from the credential's field of the same name, so a
+    /// `#[derive(Deserialize)] struct Cred { user: String, service: String }`
+    /// reads the baseline fields. Other fields are ignored.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected), when a field is missing or doesn't parse
+    ///
+    /// # Example
+    ///     #[derive(serde::Deserialize)]
+    ///     struct Cred {
+    ///         user: String,
+    ///         service: String,
+    ///     }
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let creds = search.by_deserialize::<Cred>("service", "Bar inc.");
+    #[cfg(feature = "serde")]
+    pub fn by_deserialize<T: serde::de::DeserializeOwned>(
+        &self,
+        by: &str,
+        query: &str,
+    ) -> Result<Vec<T>> {
+        let result = self.by(by, query)?;
+
+        search::results(&result)
+            .iter()
+            .map(search::deserialize_result)
+            .collect()
+    }
+    /// Specifies what parameter to search by and the words the field
+    /// must contain, in any order.
+    ///
+    /// The query is split on whitespace and each word matched ignoring
+    /// case, so `aws prod` finds `prod-aws-account`, see
+    /// [Tokens](MatchMode::Tokens). Windows and the mock backend check
+    /// every credential they list. secret-service and iOS list every
+    /// item and filter client-side, as the other non-exact match modes
+    /// do, as keyutils does with key descriptions. macOS ignores the
+    /// match mode and searches for the query as a whole. A query without any words is an
+    /// [InvalidQuery](Error::InvalidQuery).
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [InvalidQuery](Error::InvalidQuery)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_tokens("service", "aws prod");
+    pub fn by_tokens(&self, by: &str, query: &str) -> CredentialSearchResult {
+        let mut search = self.clone();
+        search.options.match_mode = MatchMode::Tokens;
+        search.by(by, query)
+    }
+    /// Searches for the credentials whose service starts with the prefix.
+    ///
+    /// The secret-service DBus search only compares attributes for
+    /// equality, so every item is listed and its `service` compared
+    /// client-side, see [Prefix](MatchMode::Prefix). Windows and the
+    /// mock backend check every credential they list, on Windows its
+    /// target, and keyutils every key description. macOS ignores the
+    /// match mode and searches for the prefix as a whole. The
+    /// comparison is case-sensitive.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.service_prefix("com.example.");
+    pub fn service_prefix(&self, prefix: &str) -> CredentialSearchResult {
+        let mut search = self.clone();
+        search.options.match_mode = MatchMode::Prefix;
+        search.by("service", prefix)
+    }
+    /// Searches for the credentials whose service ends with the suffix,
+    /// as [service_prefix](Search::service_prefix) does for a prefix.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.service_suffix(".example.com");
+    pub fn service_suffix(&self, suffix: &str) -> CredentialSearchResult {
+        let mut search = self.clone();
+        search.options.match_mode = MatchMode::Suffix;
+        search.by("service", suffix)
+    }
+    /// Specifies what parameter to search by and the query string,
+    /// returning the fields exactly as the store returned them.
+    ///
+    /// Fields aren't renamed, hidden or [selected](Search::select).
+    /// secret-service returns each item's attributes alone, keeping
+    /// `xdg:schema` and leaving out the `label` and `path` it adds
+    /// otherwise. Windows names the fields after the `CREDENTIALW`
+    /// members, with the raw target name and numeric type and
+    /// persistence. The other platforms already return their native
+    /// attributes. Useful to see why a field is missing from a search.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_raw("user", "Mr. Foo Bar");
+    pub fn by_raw(&self, by: &str, query: &str) -> CredentialSearchResult {
+        let mut search = self.clone();
+        search.options.raw = true;
+        search.timed_by(by, query).map(|result| result.results)
+    }
+    /// Lists every credential and groups those sharing an identity,
+    /// returning only the groups with more than one credential.
+    ///
+    /// A credential's identity is its service, which on Windows is its
+    /// target, along with its user, looked up as by [SearchResult::get].
+    /// Credentials missing either are left out.
+    /// Groups are ordered by identity, each ordered by ID. Apps that
+    /// recreate their entries leave such duplicates behind, which a
+    /// cleanup tool can then delete.
+    ///
+    /// iOS can't list every credential, and fails with a
+    /// [SearchError](Error::SearchError), though its keychain
+    /// already rejects a second item with the same service and account.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(duplicates) = search.find_duplicates() {
+    ///         for cluster in duplicates {
+    ///             println!("{} copies of {:?}", cluster.len(), cluster[0].get("service"));
+    ///         }
+    ///     }
+    pub fn find_duplicates(&self) -> Result<Vec<Vec<SearchResult<'static>>>> {
+        let result = self.all()?;
+
+        let mut identities: BTreeMap<(String, String), Vec<SearchResult<'static>>> =
+            BTreeMap::new();
+        for search_result in search::results(&result) {
+            let identity = match search::identity(&search_result) {
+                Some(identity) => identity,
+                None => continue,
+            };
+            identities
+                .entry(identity)
+                .or_default()
+                .push(search_result.into_owned());
+        }
+
+        Ok(identities
+            .into_values()
+            .filter(|cluster| cluster.len() > 1)
+            .collect())
+    }
+    /// Returns up to `limit` of the distinct services among every
+    /// credential, sorted alphabetically, such as for a service picker.
+    ///
+    /// The service is the baseline `service` field, secret-service's
+    /// `service` attribute and macOS' `svce`, apart from on Windows,
+    /// where credentials are named by their target, so the target name
+    /// is used. Credentials without one are left out. Backends that
+    /// can't list every credential, such as iOS, fail with a
+    /// [SearchError](Error::SearchError). An empty store has no services.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(services) = search.distinct_services(10) {
+    ///         println!("{}", services.join("\n"));
+    ///     }
+    pub fn distinct_services(&self, limit: usize) -> Result<Vec<String>> {
+        let result = self.all()?;
+        let field = self.service_field();
+
+        let services: BTreeSet<String> = search::results(&result)
+            .iter()
+            .filter_map(|search_result| search_result.get(field))
+            .filter(|service| !service.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Ok(services.into_iter().take(limit).collect())
+    }
+    // The field naming a credential's service, the target on Windows,
+    // where credentials are named by it.
+    fn service_field(&self) -> &'static str {
+        match self.backend_name() {
+            "windows" => "target",
+            _ => "service",
+        }
+    }
+    /// Counts the credentials with each distinct value of the field,
+    /// such as how many there are for each service, for summary views.
+    ///
+    /// Every credential is listed, looking the field up as
+    /// [missing_field](Search::missing_field) does. Credentials where
+    /// it is absent or empty, or Windows' `NO USER`, aren't counted.
+    /// Backends that can't list every credential, such as iOS, fail
+    /// with a [SearchError](Error::SearchError). An empty store has no values.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(counts) = search.field_cardinality("service") {
+    ///         for (service, count) in counts {
+    ///             println!("{}: {}", service, count);
+    ///         }
+    ///     }
+    pub fn field_cardinality(&self, field: &str) -> Result<HashMap<String, usize>> {
+        let result = self.all()?;
+        let native = search::canonical_field(self.backend_name(), field);
+
+        let mut counts = HashMap::new();
+        for search_result in search::results(&result) {
+            let value = native
+                .and_then(|native| search_result.get(native))
+                .or_else(|| search_result.get(field));
+            match value {
+                Some(value) if !value.is_empty() && value != search::NO_USER => {
+                    *counts.entry(value.to_string()).or_insert(0) += 1;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(counts)
+    }
+    /// Specifies what parameter to search by and the query string,
+    /// returning any non-fatal warnings alongside the results.
+    ///
+    /// Credentials that can't be read, or collections that were skipped,
+    /// are reported as warnings instead of failing the whole search.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     if let Ok(verbose) = search.by_verbose("user", "Mr. Foo Bar") {
+    ///         for warning in verbose.warnings {
+    ///             eprintln!("{}", warning);
+    ///         }
+    ///     }
+    pub fn by_verbose(&self, by: &str, query: &str) -> Result<CredentialSearchResultWithWarnings> {
+        let mut result = self.timed_by(by, query)?;
+        self.shape_results(&mut result.results, by, query);
+
+        Ok(result)
+    }
+    /// Describes what the backend would do to search by the parameter
+    /// and query string, without running the search.
+    ///
+    /// The platforms name and match fields differently, `user` is the
+    /// `acct` attribute on macOS, the `username` attribute on
+    /// secret-service, and a case-insensitive regex on Windows.
+    /// This spells out the field and matching the backend would use,
+    /// given the options set on the search, to help tell why a search missed.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     println!("{}", search.explain("user", "Mr. Foo Bar"));
+    pub fn explain(&self, by: &str, query: &str) -> String {
+        format!(
+            "{}: {}",
+            self.inner.backend_name(),
+            self.inner.explain(by, query, &self.options)
+        )
+    }
+    /// Plans the search by the parameter and query string, without
+    /// running it or touching the store.
+    ///
+    /// Nothing is connected to or enumerated, so a search that would
+    /// prompt, or take a while, can be previewed first. The plan names
+    /// the backend, where it would look, the native field and the item
+    /// class, alongside the [explanation](Search::explain), as data to
+    /// log or print for a dry run.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let plan = search.plan("user", "Mr. Foo Bar");
+    ///     println!("{} searches {}", plan.backend, plan.scope);
+    pub fn plan(&self, by: &str, query: &str) -> SearchPlan {
+        self.inner.plan(by, query, &self.options)
+    }
+    /// Searches by the given parameter and query string, then lists
+    /// the results as [list_credentials](List::list_credentials) does.
+    ///
+    /// Errors are returned rather than listed as their message, so
+    /// callers can still tell [NoResults](Error::NoResults) apart.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     use keyring_search::{Limit, Search};
+    ///     let search = Search::new().unwrap();
+    ///     if let Ok(list) = search.search_and_list("user", "Mr. Foo Bar", Limit::Max(5)) {
+    ///         println!("{}", list);
+    ///     }
+    pub fn search_and_list(&self, by: &str, query: &str, limit: Limit) -> Result<String> {
+        let result = self.by(by, query)?;
+
+        Ok(List::list_credentials(&Ok(result), limit))
+    }
+    /// Searches by each parameter and query pair, keeping the
+    /// credentials that match any of them.
+    ///
+    /// Each pair is searched as [by_user](Search::by_user) and the like
+    /// would, and the results are [merged](search::merge), dropping exact
+    /// duplicates and numbering them from 1 in the order of the pairs.
+    /// [query](Search::query) keeps those matching all of its terms instead.
+    /// Only when every pair finds nothing is it [NoResults](Error::NoResults),
+    /// any other error fails the whole search.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_any_of(&[("user", "alice"), ("service", "aws")]);
+    pub fn by_any_of(&self, criteria: &[(&str, &str)]) -> CredentialSearchResult {
+        let results = criteria.iter().map(|(by, query)| self.by(by, query));
+
+        match search::merge(results) {
+            Err(Error::NoResults) if self.options.empty_as_ok => Ok(HashMap::new()),
+            result => result,
+        }
+    }
+    /// Searches with a query of `field:value` terms, keeping the
+    /// credentials that match all of them.
+    ///
+    /// Fields are `user`, `service`, `target`, `label` and `schema`.
+    /// Every credential is listed and each term then keeps those whose
+    /// field contains the value, ignoring case. A bare word, without a
+    /// field, keeps the credentials with any field containing it, so a
+    /// query can be bare words only. Quote a value with spaces or
+    /// colons, `service:"my app"`. Result IDs are kept, so they can have
+    /// gaps.
+    ///
+    /// Can return:
+    /// [InvalidQuery](Error::InvalidQuery), when a field is unknown,
+    /// a quote is unterminated, or the query is empty
+    /// [SearchError](Error::SearchError)
+    /// [NoResults](Error::NoResults)
+    /// [Unexpected](Error::Unexpected)
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.query("user:alice service:aws");
+    pub fn query(&self, query: &str) -> CredentialSearchResult {
+        let terms = search::parse_query(query)?;
+        let first = &terms[0];
+        let by = first.field.as_deref().unwrap_or_default();
 
-pub struct Search {
-    inner: Box<CredentialSearch>,
-}
-/// The implementation of the Search structures methods.
-///
-/// The default search types are: Target, User, and Service.
-/// On linux-keyutils these all default to searching the 'session'
-/// keyring. If searching in a different keyring, utilize the
-/// platform specific `search_by_keyring` function
-impl Search {
-    /// Create a new instance of the Credential Search.
+        let mut results = self.list_unlimited()?;
+        results.retain(|id, fields| {
+            let result = SearchResult::new(id, fields);
+            terms.iter().all(|term| term.is_match(&result))
+        });
+        let mut results = self.apply_limit(results);
+        if results.is_empty() && !self.options.empty_as_ok {
+            return Err(Error::NoResults);
+        }
+        self.shape_results(&mut results, by, &first.value);
+        if self.options.tag_matched_query {
+            for fields in results.values_mut() {
+                fields.insert(search::MATCHED_QUERY_FIELD.to_string(), query.to_string());
+            }
+        }
+
+        Ok(results)
+    }
+    /// Searches by the given parameter, escalating from an exact match
+    /// to looser ones only while nothing matches.
     ///
-    /// The default credential search is used.
-    pub fn new() -> Result<Search> {
-        default_credential_search()
+    /// Each [tier](MatchTier) of [match_tiers](Search::match_tiers) is
+    /// searched in turn, by default exact, then substring, then fuzzy,
+    /// and the first one with results is returned, each result tagged
+    /// with the tier's name under `tier`. Any error other than
+    /// [NoResults](Error::NoResults) stops the escalation.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let results = search.by_smart("user", "Mr. Foo Bar");
+    pub fn by_smart(&self, by: &str, query: &str) -> CredentialSearchResult {
+        let tiers = self
+            .options
+            .match_tiers
+            .as_deref()
+            .unwrap_or(search::DEFAULT_MATCH_TIERS);
+
+        for tier in tiers {
+            let result = match tier.query(query) {
+                Some((match_mode, tier_query)) => {
+                    let mut search = self.clone();
+                    search.options.match_mode = match_mode;
+                    search.observed_by(by, &tier_query)
+                }
+                // Windows and the mock match every query as a regex, so
+                // an exact match is the whole value, case-sensitively.
+                None if matches!(self.backend_name(), "windows" | "mock") => {
+                    let mut search = self.clone();
+                    search.options.match_mode = MatchMode::Regex;
+                    search.options.regex_flags.case_insensitive = false;
+                    search.observed_by(by, &format!("^{}$", regex::escape(query)))
+                }
+                None => self.observed_by(by, query),
+            };
+            let mut results = match result {
+                Ok(result) => result.results,
+                Err(Error::NoResults) => continue,
+                Err(err) => return Err(err),
+            };
+            self.shape_results(&mut results, by, query);
+            for fields in results.values_mut() {
+                fields.insert(search::TIER_FIELD.to_string(), tier.name().to_string());
+            }
+            return Ok(results);
+        }
+
+        if self.options.empty_as_ok {
+            Ok(HashMap::new())
+        } else {
+            Err(Error::NoResults)
+        }
     }
-    /// Specifies searching by target and the query string
+    /// Returns the credentials where the field is absent or empty.
+    ///
+    /// The inverse of a search, for cleanup, such as finding credentials
+    /// without a user. Every credential is listed and checked, looking
+    /// the field up by the backend's name for it, see
+    /// [canonical_field](search::canonical_field), then as by
+    /// [SearchResult::get]. Windows' `NO USER` counts as an empty user.
+    /// Backends that can't list every credential, such as iOS, fail
+    /// with a [SearchError](Error::SearchError).
     ///
     /// Can return:
     /// [SearchError](Error::SearchError)
@@ -135,11 +1437,37 @@ impl Search {
     ///
     /// # Example
     ///     let search = keyring_search::Search::new().unwrap();
-    ///     let results = search.by_target("Foo.app");
-    pub fn by_target(&self, query: &str) -> CredentialSearchResult {
-        self.inner.by("target", query)
+    ///     let results = search.missing_field("user");
+    pub fn missing_field(&self, field: &str) -> CredentialSearchResult {
+        let mut results = self.list_unlimited()?;
+        let native = search::canonical_field(self.backend_name(), field);
+
+        results.retain(|id, fields| {
+            let result = SearchResult::new(id, fields);
+            let value = native
+                .and_then(|native| result.get(native))
+                .or_else(|| result.get(field));
+            match value {
+                Some(value) => value.is_empty() || value == search::NO_USER,
+                None => true,
+            }
+        });
+        let mut results = self.apply_limit(results);
+        if results.is_empty() && !self.options.empty_as_ok {
+            return Err(Error::NoResults);
+        }
+        self.shape_results(&mut results, field, "");
+
+        Ok(results)
     }
-    /// Specifies searching by user and the query string
+    /// Returns the credentials whose field the matcher accepts.
+    ///
+    /// Takes any [Matcher], one of the built-in exact, substring, regex,
+    /// glob and fuzzy matchers in [search], or one of your own, such as
+    /// a closure comparing versions. Every credential is listed and the
+    /// field looked up as by [missing_field](Search::missing_field), so
+    /// credentials without it don't match. Backends that can't list every
+    /// credential, such as iOS, fail with a [SearchError](Error::SearchError).
     ///
     /// Can return:
     /// [SearchError](Error::SearchError)
@@ -147,120 +1475,752 @@ impl Search {
     /// [Unexpected](Error::Unexpected)
     ///
     /// # Example
+    ///     use keyring_search::search::GlobMatcher;
     ///     let search = keyring_search::Search::new().unwrap();
-    ///     let results = search.by_user("Mr. Foo Bar");
-    pub fn by_user(&self, query: &str) -> CredentialSearchResult {
-        self.inner.by("user", query)
+    ///     let matcher = GlobMatcher::new("prod-*").unwrap();
+    ///     let results = search.by_matcher("service", &matcher);
+    pub fn by_matcher(&self, field: &str, matcher: &dyn Matcher) -> CredentialSearchResult {
+        let mut results = self.list_unlimited()?;
+        let native = search::canonical_field(self.backend_name(), field);
+
+        results.retain(|id, fields| {
+            let result = SearchResult::new(id, fields);
+            native
+                .and_then(|native| result.get(native))
+                .or_else(|| result.get(field))
+                .map_or(false, |value| matcher.matches(value))
+        });
+        let mut results = self.apply_limit(results);
+        if results.is_empty() && !self.options.empty_as_ok {
+            return Err(Error::NoResults);
+        }
+        self.shape_results(&mut results, field, "");
+
+        Ok(results)
     }
-    /// Specifies searching by service and the query string
+    /// Searches with parameters in the platform's own terms, for the
+    /// attributes the generic fields don't cover.
+    ///
+    /// The parameters are passed on as they are: on secret-service as
+    /// the attributes of the DBus search, on macOS to the `ItemSearchOptions`
+    /// setter each key names, by setter or attribute code, such as `agrp`,
+    /// and on Windows and the mock as result fields that must equal their
+    /// value. Results are still shaped by the search's options.
     ///
     /// Can return:
+    /// [UnsupportedField](Error::UnsupportedField), for a key the backend can't map
+    /// [InvalidQuery](Error::InvalidQuery), when there are no parameters
     /// [SearchError](Error::SearchError)
     /// [NoResults](Error::NoResults)
+    ///
+    /// # Example
+    ///     use std::collections::HashMap;
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let params = HashMap::from([("xdg:schema".to_string(), "org.gnome.keyring.Note".to_string())]);
+    ///     let results = search.native_query(params);
+    pub fn native_query(&self, params: HashMap<String, String>) -> CredentialSearchResult {
+        if params.is_empty() {
+            return Err(Error::InvalidQuery(
+                "a native query needs at least one parameter".to_string(),
+            ));
+        }
+        let mut results = match self.inner.native_query(&params, &self.options) {
+            Ok(result) => result.results,
+            Err(Error::NoResults) if self.options.empty_as_ok => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        self.shape_results(&mut results, "", "");
+
+        Ok(results)
+    }
+    /// Creates an [IncrementalSearch] over every credential this
+    /// search's backend lists, matching the given field.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
     /// [Unexpected](Error::Unexpected)
     ///
     /// # Example
     ///     let search = keyring_search::Search::new().unwrap();
-    ///     let results = search.by_service("Bar inc.");
-    pub fn by_service(&self, query: &str) -> CredentialSearchResult {
-        self.inner.by("service", query)
+    ///     if let Ok(incremental) = search.incremental("user") {
+    ///         println!("{}", incremental.query("Mr. F").len());
+    ///     }
+    pub fn incremental(&self, field: &str) -> Result<IncrementalSearch> {
+        Ok(IncrementalSearch {
+            results: self.all()?,
+            search: self.clone(),
+            field: field.to_string(),
+        })
+    }
+    /// Creates a [SearchCursor] over the results of searching the field
+    /// for the query, to be returned a batch at a time.
+    ///
+    /// Nothing is searched until the first batch is asked for.
+    ///
+    /// # Example
+    ///     let search = keyring_search::Search::new().unwrap();
+    ///     let mut cursor = search.cursor("user", "Mr. Foo Bar");
+    ///     if let Ok(batch) = cursor.next_batch(50) {
+    ///         println!("{}", batch.len());
+    ///     }
+    pub fn cursor(&self, field: &str, query: &str) -> SearchCursor {
+        SearchCursor {
+            search: self.clone(),
+            field: field.to_string(),
+            query: query.to_string(),
+            position: 0,
+            done: false,
+        }
+    }
+    // Lists every credential, with the field selection set on the search,
+    // an empty store is an empty result.
+    fn all(&self) -> Result<HashMap<String, HashMap<String, String>>> {
+        let mut results = self.list_unlimited()?;
+        self.shape_results(&mut results, "", "");
+
+        Ok(results)
+    }
+    // Lists every credential, ignoring the limit, which applies to the
+    // credentials the listing is filtered down to. An empty store is an
+    // empty result.
+    fn list_unlimited(&self) -> Result<HashMap<String, HashMap<String, String>>> {
+        let options = SearchOptions {
+            limit: Limit::All,
+            ..self.options.clone()
+        };
+        match self.inner.all(&options) {
+            Ok(result) => Ok(result.results),
+            Err(Error::NoResults) => Ok(HashMap::new()),
+            Err(err) => Err(err),
+        }
+    }
+    // Keeps the first results by ID a Max limit allows, keeping their IDs.
+    fn apply_limit(
+        &self,
+        results: HashMap<String, HashMap<String, String>>,
+    ) -> HashMap<String, HashMap<String, String>> {
+        match self.options.limit {
+            Limit::Max(max) => search::sorted_by_id(results)
+                .into_iter()
+                .take(max)
+                .collect(),
+            Limit::All | Limit::Bytes(_) => results,
+        }
+    }
+    // Applies the field selection and relevance scores set on the search.
+    fn shape_results(
+        &self,
+        results: &mut HashMap<String, HashMap<String, String>>,
+        by: &str,
+        query: &str,
+    ) {
+        search::normalize_fields(results, self.inner.backend_name());
+        // Scored first, so the searched field counts even when it isn't selected.
+        if self.options.ranked {
+            search::score_results(results, by, query);
+        }
+        match (&self.options.select, &self.options.hide_fields) {
+            (Some(fields), _) => search::select_fields(results, fields),
+            (None, Some(fields)) => search::hide_fields(results, fields),
+            (None, None) => search::hide_fields(results, search::DEFAULT_HIDDEN_FIELDS),
+        }
+        if self.options.tag_matched_query {
+            for fields in results.values_mut() {
+                fields.insert(search::MATCHED_QUERY_FIELD.to_string(), query.to_string());
+            }
+        }
+    }
+    // Performs the backend search, turning NoResults into an empty result if asked to.
+    fn timed_by(&self, by: &str, query: &str) -> Result<CredentialSearchResultWithWarnings> {
+        match self.observed_by(by, query) {
+            Err(Error::NoResults) if self.options.empty_as_ok => {
+                Ok(CredentialSearchResultWithWarnings::default())
+            }
+            result => result,
+        }
+    }
+    // Performs the backend search, reporting its timing when there is an observer.
+    fn observed_by(&self, by: &str, query: &str) -> Result<CredentialSearchResultWithWarnings> {
+        let observer = match &self.observer {
+            Some(observer) => observer,
+            None => return self.inner.by_with_options(by, query, &self.options),
+        };
+
+        let start = Instant::now();
+        let result = self.inner.by_with_options(by, query, &self.options);
+        observer(SearchTiming {
+            backend: self.inner.backend_name(),
+            field: by.to_string(),
+            query_len: query.len(),
+            duration: start.elapsed(),
+            result_count: result.as_ref().map_or(0, |result| result.results.len()),
+        });
+
+        result
+    }
+}
+
+// Whether a value can be a netrc token, which ends at whitespace.
+#[cfg(feature = "keyring")]
+fn is_netrc_token(value: &str) -> bool {
+    !value.is_empty() && !value.chars().any(char::is_whitespace)
+}
+
+/// A search-as-you-type over a cached listing of every credential.
+///
+/// The store is listed once, when created with [new](IncrementalSearch::new)
+/// or [Search::incremental], then each [query](IncrementalSearch::query)
+/// filters the cache, so a search box can run one per keystroke without
+/// listing the store again. [refresh](IncrementalSearch::refresh) re-reads
+/// the store. Backends that can't list every credential, such as iOS,
+/// fail to create one.
+#[derive(Debug)]
+pub struct IncrementalSearch {
+    search: Search,
+    field: String,
+    results: HashMap<String, HashMap<String, String>>,
+}
+
+impl IncrementalSearch {
+    /// Lists every credential in the platform default store,
+    /// to be matched on the given field.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    pub fn new(field: &str) -> Result<IncrementalSearch> {
+        Search::default().incremental(field)
+    }
+    /// Returns the cached credentials whose field contains the
+    /// query, ignoring case, ordered by ID.
+    ///
+    /// The field is looked up by the backend's name for it, see
+    /// [canonical_field](search::canonical_field), then as by
+    /// [SearchResult::get]. An empty query returns every credential.
+    /// A [Max](Limit::Max) limit set on the search keeps the first matches.
+    pub fn query(&self, partial: &str) -> Vec<SearchResult<'_>> {
+        let partial = partial.to_lowercase();
+        let native = search::canonical_field(self.search.backend_name(), &self.field);
+
+        search::results(&self.results)
+            .into_iter()
+            .filter(|result| {
+                native
+                    .and_then(|native| result.get(native))
+                    .or_else(|| result.get(&self.field))
+                    .map_or(false, |value| value.to_lowercase().contains(&partial))
+            })
+            .take(self.search.options.limit.max_items())
+            .collect()
+    }
+    /// Lists the store again, replacing the cache.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    pub fn refresh(&mut self) -> Result<()> {
+        self.results = self.search.all()?;
+        Ok(())
     }
 }
 
+/// A search that returns its results a batch at a time, for stores
+/// too large to hold every result at once.
+///
+/// Created with [Search::cursor], the cursor counts the matches it has
+/// returned, and each [next_batch](SearchCursor::next_batch) searches
+/// again, passing over that many. Windows and secret-service skip them
+/// as they enumerate, so a batch only holds its own results. Other
+/// backends search up to the end of the batch and drop the rest.
+///
+/// The position is a count of matches in the store's order, not a handle
+/// on the store, so nothing is held open between batches. A credential
+/// added or removed before the position mid-scroll shifts the matches
+/// after it, so the next batch can skip one or repeat one. The search's
+/// [limit](Search::with_limit) is replaced by each batch's size.
+#[derive(Debug)]
+pub struct SearchCursor {
+    search: Search,
+    field: String,
+    query: String,
+    position: usize,
+    done: bool,
+}
+
+impl SearchCursor {
+    /// Returns up to `n` of the next results, ordered by ID, numbering
+    /// them on from the previous batch. Once the results run out, the
+    /// batch is empty.
+    ///
+    /// Can return:
+    /// [SearchError](Error::SearchError)
+    /// [Unexpected](Error::Unexpected)
+    pub fn next_batch(&mut self, n: usize) -> Result<Vec<SearchResult<'static>>> {
+        if self.done || n == 0 {
+            return Ok(Vec::new());
+        }
+        let mut results = match self.search.inner.by_page(
+            &self.field,
+            &self.query,
+            &self.search.options,
+            self.position,
+            n,
+        ) {
+            Ok(result) => result.results,
+            Err(Error::NoResults) => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        self.search
+            .shape_results(&mut results, &self.field, &self.query);
+
+        let batch: Vec<SearchResult<'static>> = search::sorted_by_id(results)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, fields))| {
+                SearchResult::new(&(self.position + index + 1).to_string(), &fields).into_owned()
+            })
+            .collect();
+        self.position += batch.len();
+        self.done = batch.len() < n;
+
+        Ok(batch)
+    }
+    /// The number of results returned so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+#[derive(Debug)]
 pub struct List {}
 
+/// The version of the JSON output's shape, see [list_json](List::list_json).
+#[cfg(feature = "serde")]
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+// The JSON output's envelope.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonOutput {
+    schema_version: u32,
+    results: Vec<JsonResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonResult {
+    id: String,
+    fields: std::collections::BTreeMap<String, String>,
+}
+
 /// Implementation of methods for the `List` structure.
 ///
-/// `list_all`, lists all returned credentials
-/// `list_max`, lists a specified max amount of
-/// credentials. These are specified by calling [list_credentials](List::list_credentials).
-///
-/// Linux-keyutils search feature is limited to one result,
-/// no matter the `Limit`, one result will be returned.
+/// [list_credentials](List::list_credentials) lists all returned
+/// credentials, a specified max amount of them, or those fitting a
+/// size budget, as the [Limit] passed specifies.
 impl List {
     /// List the credentials with given search result
     ///
     /// Takes CredentialSearchResult type and converts to a string
     /// for printing. Matches the Limit type passed to constrain
-    /// the amount of results added to the string
+    /// the amount of results added to the string. A
+    /// [Bytes](Limit::Bytes) budget is approximate, it counts the
+    /// metadata text only.
     pub fn list_credentials(search_result: &CredentialSearchResult, limit: Limit) -> String {
-        match limit {
-            Limit::All => Self::list_all(search_result),
-            Limit::Max(max) => Self::list_max(search_result, max),
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(err) => return err.to_string(),
+        };
+
+        let mut output = String::new();
+        for (outer_key, metadata) in Self::limit_entries(Self::sorted_entries(search_result), limit)
+        {
+            output.push_str(&Self::format_entry(&outer_key, &metadata));
+        }
+        output
+    }
+    /// Write the credentials to a writer, one block at a time.
+    ///
+    /// Produces the same blocks as [list_credentials](List::list_credentials),
+    /// but writes each straight to the writer, such as stdout or a file,
+    /// instead of building the whole listing in memory. An error result
+    /// writes its message.
+    pub fn write_all(
+        search_result: &CredentialSearchResult,
+        limit: Limit,
+        w: &mut impl Write,
+    ) -> io::Result<()> {
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(err) => return writeln!(w, "{}", err),
+        };
+
+        for id in Self::limit_ids(search_result, limit) {
+            let inner_map = &search_result[id];
+            let mut metadata: Vec<(&String, &String)> = inner_map.iter().collect();
+            metadata.sort_by(|a, b| a.0.cmp(b.0));
+            writeln!(w, "{}", id)?;
+            for (key, value) in metadata {
+                writeln!(w, "{}: {}", key, value)?;
+            }
+        }
+
+        Ok(())
+    }
+    /// List the credentials ordered by relevance.
+    ///
+    /// Credentials are sorted ascending by the `score` field a
+    /// [ranked](Search::ranked) search stores, best match first,
+    /// then by ID. Those without a score are listed last. The Limit
+    /// type constrains the results as it does for [list_credentials](List::list_credentials).
+    pub fn list_ranked(search_result: &CredentialSearchResult, limit: Limit) -> String {
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(err) => return err.to_string(),
+        };
+
+        let mut entries = Self::sorted_entries(search_result);
+        entries.sort_by_key(|(_, metadata)| {
+            metadata
+                .iter()
+                .find(|(key, _)| key == search::SCORE_FIELD)
+                .and_then(|(_, score)| score.parse::<usize>().ok())
+                .unwrap_or(usize::MAX)
+        });
+
+        let mut output = String::new();
+        for (outer_key, metadata) in Self::limit_entries(entries, limit) {
+            output.push_str(&Self::format_entry(&outer_key, &metadata));
         }
+        output
     }
-    /// List all credential search results.
+    /// List at most `max` credentials from each group of a field.
     ///
-    /// Is the result of passing the Limit::All type
-    /// to list_credentials.
-    fn list_all(result: &CredentialSearchResult) -> String {
+    /// Credentials are grouped by the field's value, looked up as by
+    /// [SearchResult::get], those without it forming one group. Each
+    /// group keeps its first credentials by ID, so a summary shows
+    /// credentials from as many services as possible rather than many
+    /// from one. A `max` of 0 lists nothing, as [Max(0)](Limit::Max) does.
+    ///
+    /// # Example
+    ///     use keyring_search::{List, Search};
+    ///     let result = Search::default().by_user("test-user");
+    ///     let summary = List::list_per_group(&result, "service", 1);
+    pub fn list_per_group(
+        search_result: &CredentialSearchResult,
+        field: &str,
+        max: usize,
+    ) -> String {
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(err) => return err.to_string(),
+        };
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
         let mut output = String::new();
-        match result {
-            Ok(search_result) => {
-                let mut entries: Vec<(String, HashMap<String, String>)> = search_result
+        for (outer_key, metadata) in Self::sorted_entries(search_result) {
+            let group = SearchResult::new(&outer_key, &search_result[&outer_key])
+                .get(field)
+                .unwrap_or_default()
+                .to_string();
+            let count = counts.entry(group).or_default();
+            if *count < max {
+                *count += 1;
+                output.push_str(&Self::format_entry(&outer_key, &metadata));
+            }
+        }
+        output
+    }
+    /// List the credentials as an aligned text table.
+    ///
+    /// Renders one row per credential, its ID followed by the given
+    /// columns, padded to the widest value in each. Columns match
+    /// keys case-insensitively, fields a credential doesn't have
+    /// render empty. The Limit type constrains the rows as it does
+    /// for [list_credentials](List::list_credentials).
+    ///
+    /// # Example
+    ///     use keyring_search::{Limit, List, Search};
+    ///     let result = Search::default().by_user("test-user");
+    ///     let table = List::list_table(&result, Limit::All, &["user", "service"]);
+    pub fn list_table(
+        search_result: &CredentialSearchResult,
+        limit: Limit,
+        columns: &[&str],
+    ) -> String {
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(err) => return err.to_string(),
+        };
+
+        let mut rows: Vec<Vec<String>> = vec![std::iter::once("ID")
+            .chain(columns.iter().copied())
+            .map(str::to_string)
+            .collect()];
+        for (outer_key, metadata) in Self::limit_entries(Self::sorted_entries(search_result), limit)
+        {
+            let mut row = vec![outer_key];
+            for column in columns {
+                let value = metadata
                     .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
-                entries.sort_by_key(|(k, _)| k.parse::<i32>().unwrap_or(0));
-
-                for (outer_key, inner_map) in entries {
-                    output.push_str(&format!("{}\n", outer_key));
-                    let mut metadata: Vec<(String, String)> = inner_map
-                        .iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    metadata.sort_by(|a, b| a.0.cmp(&b.0));
-                    for (key, value) in metadata {
-                        output.push_str(&format!("{}: {}\n", key, value));
-                    }
-                }
-                println!("Search returned {} results\n", search_result.keys().len());
-                output
+                    .find(|(key, _)| key.eq_ignore_ascii_case(column))
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default();
+                row.push(value);
+            }
+            rows.push(row);
+        }
+
+        let widths: Vec<usize> = (0..=columns.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut output = String::new();
+        for row in rows {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{:<width$}", value, width = width))
+                .collect();
+            output.push_str(line.join("  ").trim_end());
+            output.push('\n');
+        }
+        output
+    }
+    /// List the credentials as JSON.
+    ///
+    /// The output is an envelope, `{ "schema_version": 1, "results": [...] }`,
+    /// each result an object of its `id` and its `fields`, ordered by ID with
+    /// fields sorted by key. A failed search lists no results along with an
+    /// `error` message, no results is just an empty list. The Limit type
+    /// constrains the results as it does for [list_credentials](List::list_credentials).
+    ///
+    /// `schema_version` is [JSON_SCHEMA_VERSION], bumped on any breaking
+    /// change to this shape, so consumers can check it before parsing.
+    ///
+    /// # Example
+    ///     use keyring_search::{Limit, List, Search};
+    ///     let result = Search::default().by_user("test-user");
+    ///     let json = List::list_json(&result, Limit::All);
+    #[cfg(feature = "serde")]
+    pub fn list_json(search_result: &CredentialSearchResult, limit: Limit) -> String {
+        let (results, error) = match search_result {
+            Ok(search_result) => (
+                Self::limit_entries(Self::sorted_entries(search_result), limit)
+                    .into_iter()
+                    .map(|(id, metadata)| JsonResult {
+                        id,
+                        fields: metadata.into_iter().collect(),
+                    })
+                    .collect(),
+                None,
+            ),
+            Err(Error::NoResults) => (Vec::new(), None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+
+        let output = JsonOutput {
+            schema_version: JSON_SCHEMA_VERSION,
+            results,
+            error,
+        };
+        serde_json::to_string(&output).unwrap_or_else(|err| err.to_string())
+    }
+    /// Write the credentials to a writer as JSON lines, one object per credential.
+    ///
+    /// Each line is a flat object of the credential's fields along with
+    /// its `id`, unless it has an `id` field of its own, ready for tools
+    /// such as `jq -c`. Like [write_all](List::write_all) each line is
+    /// written as it is built, so large listings aren't held in memory.
+    /// No results write nothing, other errors write `{"error": "..."}`.
+    /// The Limit type constrains the lines as it does for
+    /// [list_credentials](List::list_credentials).
+    ///
+    /// # Example
+    ///     use keyring_search::{Limit, List, Search};
+    ///     let result = Search::default().by_user("test-user");
+    ///     List::to_jsonl(&result, Limit::All, &mut std::io::stdout()).unwrap();
+    #[cfg(feature = "serde")]
+    pub fn to_jsonl(
+        search_result: &CredentialSearchResult,
+        limit: Limit,
+        w: &mut impl Write,
+    ) -> io::Result<()> {
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(Error::NoResults) => return Ok(()),
+            Err(err) => {
+                let error = BTreeMap::from([("error", err.to_string())]);
+                serde_json::to_writer(&mut *w, &error)?;
+                return writeln!(w);
             }
-            Err(err) => err.to_string(),
+        };
+
+        for id in Self::limit_ids(search_result, limit) {
+            let mut line: BTreeMap<&str, &str> = search_result[id]
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            line.entry("id").or_insert(id);
+            serde_json::to_writer(&mut *w, &line)?;
+            writeln!(w)?;
         }
+
+        Ok(())
     }
-    /// List a certain amount of credential search results.
+    /// List the credentials in the `.netrc` format, for git and curl.
+    ///
+    /// Each credential with a service and a user becomes a line,
+    /// `machine <service> login <user>`. Search results don't hold
+    /// secrets, short of [include_blob](Search::include_blob), so the
+    /// `password <secret>` is only added with `with_secrets`, which reads
+    /// each one through the [keyring::Entry] for the credential, see
+    /// [SearchResult::to_entry]. That reads every listed password, so pass
+    /// only the results you mean to export. Credentials without a service
+    /// or user, or with a secret asked for that can't be read, are skipped,
+    /// as are those with whitespace in any of them, which netrc can't hold.
+    /// On Windows the service is taken from the keyring-rs target. A failed
+    /// search lists nothing. The Limit type constrains the credentials
+    /// listed as it does for [list_credentials](List::list_credentials).
     ///
-    /// Is the result of passing the Limit::Max(i64) type
-    /// to list_credentials. The 64 bit integer represents
-    /// the total of the results passed.
-    fn list_max(result: &CredentialSearchResult, max: i64) -> String {
+    /// # Example
+    ///     use std::collections::HashMap;
+    ///     use keyring_search::{Limit, List};
+    ///     let credential = HashMap::from([
+    ///         ("service".to_string(), "github.com".to_string()),
+    ///         ("user".to_string(), "octocat".to_string()),
+    ///     ]);
+    ///     let result = Ok(HashMap::from([("1".to_string(), credential)]));
+    ///     let netrc = List::to_netrc(&result, Limit::All, false);
+    ///     assert_eq!("machine github.com login octocat\n", netrc);
+    #[cfg(feature = "keyring")]
+    pub fn to_netrc(
+        search_result: &CredentialSearchResult,
+        limit: Limit,
+        with_secrets: bool,
+    ) -> String {
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(_) => return String::new(),
+        };
+
         let mut output = String::new();
-        let mut count = 1;
-        match result {
-            Ok(search_result) => {
-                let mut entries: Vec<(String, HashMap<String, String>)> = search_result
+        for id in Self::limit_ids(search_result, limit) {
+            let result = SearchResult::new(id, &search_result[id]);
+            let (service, user) = match result.entry_fields() {
+                Ok(fields) => fields,
+                Err(_) => continue,
+            };
+            if [service, user].iter().any(|token| !is_netrc_token(token)) {
+                continue;
+            }
+            if !with_secrets {
+                output.push_str(&format!("machine {} login {}\n", service, user));
+                continue;
+            }
+            let password = match result.to_entry().and_then(|entry| entry.get_password()) {
+                Ok(password) if is_netrc_token(&password) => password,
+                _ => continue,
+            };
+            output.push_str(&format!(
+                "machine {} login {} password {}\n",
+                service, user, password
+            ));
+        }
+        output
+    }
+    // The IDs allowed by the limit, in order, borrowed rather than
+    // copying the results.
+    fn limit_ids(
+        search_result: &HashMap<String, HashMap<String, String>>,
+        limit: Limit,
+    ) -> Vec<&String> {
+        let mut ids: Vec<&String> = search_result.keys().collect();
+        ids.sort_by_key(|id| id.parse::<i32>().unwrap_or(0));
+
+        Self::take_limit(ids, limit, |id| {
+            Self::entry_size(id, search_result[*id].iter())
+        })
+    }
+    // Keeps the leading entries allowed by the limit.
+    fn limit_entries(
+        entries: Vec<(String, Vec<(String, String)>)>,
+        limit: Limit,
+    ) -> Vec<(String, Vec<(String, String)>)> {
+        Self::take_limit(entries, limit, |(outer_key, metadata)| {
+            Self::entry_size(outer_key, metadata.iter().map(|(key, value)| (key, value)))
+        })
+    }
+    // Keeps the leading items allowed by the limit, sized by `size` for
+    // a Bytes budget.
+    fn take_limit<T>(items: Vec<T>, limit: Limit, size: impl Fn(&T) -> usize) -> Vec<T> {
+        match limit {
+            Limit::All => items,
+            Limit::Max(max) => items.into_iter().take(max).collect(),
+            Limit::Bytes(max_bytes) => {
+                let mut bytes = 0;
+                items
+                    .into_iter()
+                    .take_while(|item| {
+                        bytes += size(item);
+                        bytes <= max_bytes
+                    })
+                    .collect()
+            }
+        }
+    }
+    // The length of a result's ID, and of every key and value, which a
+    // Bytes budget counts.
+    fn entry_size<'a>(
+        outer_key: &str,
+        fields: impl Iterator<Item = (&'a String, &'a String)>,
+    ) -> usize {
+        outer_key.len()
+            + fields
+                .map(|(key, value)| key.len() + value.len())
+                .sum::<usize>()
+    }
+    // Sorts the results by ID, and each result's metadata by key.
+    fn sorted_entries(
+        search_result: &HashMap<String, HashMap<String, String>>,
+    ) -> Vec<(String, Vec<(String, String)>)> {
+        let mut entries: Vec<(String, Vec<(String, String)>)> = search_result
+            .iter()
+            .map(|(outer_key, inner_map)| {
+                let mut metadata: Vec<(String, String)> = inner_map
                     .iter()
                     .map(|(k, v)| (k.clone(), v.clone()))
                     .collect();
-                entries.sort_by_key(|(k, _)| k.parse::<i32>().unwrap_or(0));
-
-                for (outer_key, inner_map) in entries {
-                    output.push_str(&format!("{}\n", outer_key));
-                    let mut metadata: Vec<(String, String)> = inner_map
-                        .iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    metadata.sort_by(|a, b| a.0.cmp(&b.0));
-                    for (key, value) in metadata {
-                        output.push_str(&format!("{}: {}\n", key, value));
-                    }
-                    count += 1;
-                    if count > max {
-                        break;
-                    }
-                }
-                println!("Search returned {} results\n", search_result.keys().len());
-                output
-            }
-            Err(err) => err.to_string(),
+                metadata.sort_by(|a, b| a.0.cmp(&b.0));
+                (outer_key.clone(), metadata)
+            })
+            .collect();
+        entries.sort_by_key(|(k, _)| k.parse::<i32>().unwrap_or(0));
+        entries
+    }
+    // Formats a single result as its ID followed by a `key: value` line per field.
+    fn format_entry(outer_key: &str, metadata: &[(String, String)]) -> String {
+        let mut output = format!("{}\n", outer_key);
+        for (key, value) in metadata {
+            output.push_str(&format!("{}: {}\n", key, value));
         }
+        output
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::mock::{self, CredentialStore, MockData};
+    use super::{BackendHealth, Error, Limit, List, Search};
+    use std::collections::HashMap;
+
     pub fn generate_random_string_of_len(len: usize) -> String {
         // from the Rust Cookbook:
         // https://rust-lang-nursery.github.io/rust-cookbook/algorithms/randomness.html
@@ -275,4 +2235,289 @@ mod tests {
     pub fn generate_random_string() -> String {
         generate_random_string_of_len(30)
     }
+
+    // A search of the mock backend, for tests holding a mock::test_scope.
+    pub fn mock_search() -> Search {
+        super::set_default_credential_search(mock::default_credential_search())
+            .expect("Failed to create mock search")
+    }
+
+    // Builds a result of `count` credentials, each with a single 10 byte field.
+    fn search_result(count: usize) -> crate::CredentialSearchResult {
+        let mut outer_map = HashMap::new();
+        for id in 1..=count {
+            let mut inner_map = HashMap::new();
+            inner_map.insert("user".to_string(), "foobar".to_string());
+            outer_map.insert(id.to_string(), inner_map);
+        }
+        Ok(outer_map)
+    }
+
+    #[test]
+    fn test_list_bytes() {
+        // Each credential is 11 bytes, its 1 byte ID and 10 byte field.
+        let list = List::list_credentials(&search_result(3), Limit::Bytes(25));
+
+        assert_eq!("1\nuser: foobar\n2\nuser: foobar\n", list);
+    }
+
+    #[test]
+    fn test_list_max() {
+        assert_eq!("", List::list_credentials(&search_result(3), Limit::Max(0)));
+        assert_eq!(
+            "1\nuser: foobar\n2\nuser: foobar\n",
+            List::list_credentials(&search_result(3), Limit::Max(2))
+        );
+        assert_eq!(
+            List::list_credentials(&search_result(3), Limit::All),
+            List::list_credentials(&search_result(3), Limit::Max(5))
+        );
+    }
+
+    #[test]
+    fn test_list_max_one() {
+        let list = List::list_credentials(&search_result(3), Limit::Max(1));
+
+        assert_eq!("1\nuser: foobar\n", list);
+    }
+
+    #[test]
+    fn test_write_all() {
+        for limit in [Limit::All, Limit::Max(0), Limit::Max(2), Limit::Bytes(25)] {
+            let mut output = Vec::new();
+            List::write_all(&search_result(3), limit, &mut output)
+                .expect("Failed to write results");
+
+            assert_eq!(
+                List::list_credentials(&search_result(3), limit),
+                String::from_utf8(output).expect("Output is not UTF-8")
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_available() {
+        let backends = Search::backends_available();
+
+        assert!(backends.contains(&"mock"));
+        assert!(backends.contains(&Search::default().backend_name()));
+    }
+
+    #[test]
+    fn test_is_mock_fallback() {
+        let _g = mock::test_scope();
+        let default = Search::default();
+        assert_eq!(default.backend_name() == "mock", default.is_mock_fallback());
+
+        let mock = mock_search();
+        assert_eq!(super::MOCK_FALLBACK, mock.is_mock_fallback());
+    }
+
+    #[test]
+    fn test_mock_by() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        mock::get_store().add(MockData::builder().service(&name).build());
+        let search = mock_search();
+
+        let result = search
+            .by("service", &name)
+            .expect("Failed to search by service");
+        assert_eq!(1, result.len());
+        assert_eq!(Some(&name), result["1"].get("Service"));
+        assert!(matches!(
+            search.by("label", &name),
+            Err(Error::Unexpected(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_health_check() {
+        let _g = mock::test_scope();
+        let search = mock_search();
+        let health = search.health_check().expect("Failed to check mock health");
+
+        assert_eq!(
+            BackendHealth {
+                backend: "mock",
+                available: true,
+                ..Default::default()
+            },
+            health
+        );
+    }
+
+    #[test]
+    fn test_list_per_group() {
+        let mut outer_map = HashMap::new();
+        for (id, service) in [("1", "mail"), ("2", "mail"), ("3", "vpn"), ("4", "mail")] {
+            let mut inner_map = HashMap::new();
+            inner_map.insert("Service".to_string(), service.to_string());
+            outer_map.insert(id.to_string(), inner_map);
+        }
+        let result = Ok(outer_map);
+
+        assert_eq!(
+            "1\nService: mail\n3\nService: vpn\n",
+            List::list_per_group(&result, "service", 1)
+        );
+        assert_eq!("", List::list_per_group(&result, "service", 0));
+        assert_eq!(
+            3,
+            List::list_per_group(&result, "service", 2)
+                .matches("Service")
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_list_table() {
+        let mut outer_map = HashMap::new();
+        let mut first = HashMap::new();
+        first.insert("User".to_string(), "alice".to_string());
+        first.insert("Service".to_string(), "mail".to_string());
+        outer_map.insert("1".to_string(), first);
+        let mut second = HashMap::new();
+        second.insert("User".to_string(), "bob".to_string());
+        outer_map.insert("2".to_string(), second);
+
+        let table = List::list_table(&Ok(outer_map), Limit::All, &["user", "service"]);
+
+        assert_eq!("ID  user   service\n1   alice  mail\n2   bob\n", table);
+    }
+
+    #[test]
+    fn test_search_debug() {
+        let _g = mock::test_scope();
+        let search = mock_search();
+
+        assert!(format!("{:?}", search).starts_with("Search { backend: \"mock\""));
+        assert_eq!("List", format!("{:?}", List {}));
+    }
+
+    #[test]
+    fn test_search_clone() {
+        let _g = mock::test_scope();
+        let search = mock_search().select(&["user"]);
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let search = search.clone();
+                std::thread::spawn(move || search.backend_name())
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!("mock", handle.join().expect("Search thread panicked"));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_config() {
+        let config: crate::SearchConfig = serde_json::from_str(
+            r#"{ "backend": "mock", "match_mode": "IgnoreCase", "limit": { "Max": 5 } }"#,
+        )
+        .expect("Failed to parse config");
+        let search = Search::from_config(config).expect("Failed to build search");
+        let saved = search.config();
+
+        assert_eq!(Some("mock"), saved.backend.as_deref());
+        assert_eq!(crate::MatchMode::IgnoreCase, saved.options.match_mode);
+        assert_eq!(Limit::Max(5), saved.options.limit);
+        assert!(saved.options.regex_flags.case_insensitive);
+
+        let unknown = crate::SearchConfig {
+            backend: Some("unknown".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Search::from_config(unknown),
+            Err(crate::Error::Unexpected(_))
+        ));
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_to_netrc() {
+        assert!(super::is_netrc_token("s3cr#t"));
+        assert!(!super::is_netrc_token("my secret"));
+        assert!(!super::is_netrc_token(""));
+
+        // Only the third has a service and a user netrc can hold, and on
+        // Windows the fourth's service comes from its keyring-rs target.
+        let mut outer_map = HashMap::new();
+        outer_map.insert(
+            "1".to_string(),
+            HashMap::from([("user".to_string(), "foobar".to_string())]),
+        );
+        outer_map.insert(
+            "2".to_string(),
+            HashMap::from([
+                ("service".to_string(), "my host".to_string()),
+                ("user".to_string(), "foobar".to_string()),
+            ]),
+        );
+        outer_map.insert(
+            "3".to_string(),
+            HashMap::from([
+                ("service".to_string(), "example.com".to_string()),
+                ("user".to_string(), "foobar".to_string()),
+            ]),
+        );
+        outer_map.insert(
+            "4".to_string(),
+            HashMap::from([
+                ("Target".to_string(), "foobar.example.org".to_string()),
+                (
+                    "Raw Target".to_string(),
+                    "LegacyGeneric:target=foobar.example.org".to_string(),
+                ),
+                ("User".to_string(), "foobar".to_string()),
+            ]),
+        );
+        let outer_map = Ok(outer_map);
+        assert_eq!(
+            "machine example.com login foobar\nmachine example.org login foobar\n",
+            List::to_netrc(&outer_map, Limit::All, false)
+        );
+        assert_eq!(
+            "machine example.com login foobar\n",
+            List::to_netrc(&outer_map, Limit::Max(3), false)
+        );
+        assert_eq!(
+            "",
+            List::to_netrc(&Err(crate::Error::NoResults), Limit::All, true)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_jsonl() {
+        let mut output = Vec::new();
+        List::to_jsonl(&search_result(3), Limit::Max(2), &mut output)
+            .expect("Failed to write JSON lines");
+        let mut empty = Vec::new();
+        List::to_jsonl(&Err(crate::Error::NoResults), Limit::All, &mut empty)
+            .expect("Failed to write JSON lines");
+
+        assert_eq!(
+            "{\"id\":\"1\",\"user\":\"foobar\"}\n{\"id\":\"2\",\"user\":\"foobar\"}\n",
+            String::from_utf8(output).expect("JSON lines should be UTF-8")
+        );
+        assert!(empty.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_list_json() {
+        let json = List::list_json(&search_result(2), Limit::Max(1));
+        let empty = List::list_json(&Err(crate::Error::NoResults), Limit::All);
+
+        assert_eq!(
+            r#"{"schema_version":1,"results":[{"id":"1","fields":{"user":"foobar"}}]}"#,
+            json
+        );
+        assert_eq!(r#"{"schema_version":1,"results":[]}"#, empty);
+    }
 }
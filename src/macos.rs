@@ -1,8 +1,20 @@
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::data::CFData;
+use core_foundation::date::{CFAbsoluteTime, CFDate};
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
 use security_framework::item;
+use security_framework::os::macos::item::ItemSearchOptionsExt;
+use security_framework::os::macos::keychain::{SecKeychain, SecPreferencesDomain};
 use std::collections::HashMap;
 
 use super::error::{Error as ErrorCode, Result};
-use super::search::{CredentialSearch, CredentialSearchApi, CredentialSearchResult};
+use super::search::{
+    base64, canonical_field, BackendHealth, CredentialSearch, CredentialSearchApi,
+    CredentialSearchResult, CredentialSearchResultWithWarnings, ItemClassKind, Keychain, Limit,
+    SearchOptions, SearchPlan,
+};
 
 pub struct MacCredentialSearch {}
 
@@ -17,26 +29,214 @@ pub fn default_credential_search() -> Box<CredentialSearch> {
 
 impl CredentialSearchApi for MacCredentialSearch {
     fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
-        search(by, query)
+        search(by, query, &SearchOptions::default())
     }
+
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        search(by, query, options).map(|results| CredentialSearchResultWithWarnings {
+            results,
+            ..Default::default()
+        })
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "macos"
+    }
+
+    fn native_query(
+        &self,
+        params: &HashMap<String, String>,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        native_query(params, options).map(|results| CredentialSearchResultWithWarnings {
+            results,
+            ..Default::default()
+        })
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        let scope = match &options.keychain {
+            None => "every keychain in the search list".to_string(),
+            Some(Keychain::Login) => "the login keychain".to_string(),
+            Some(Keychain::System) => "the System keychain".to_string(),
+            Some(Keychain::Common) => "the common keychain".to_string(),
+            Some(Keychain::Path(path)) => format!("the keychain at {}", path),
+        };
+        SearchPlan {
+            item_class: Some(item_class(options)),
+            ..SearchPlan::new(self, by, query, options, &scope)
+        }
+    }
+
+    fn all(&self, options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        // A query without attributes matches every item of the class.
+        query_keychain(&[], options).map(|results| CredentialSearchResultWithWarnings {
+            results,
+            ..Default::default()
+        })
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        Ok(health_check())
+    }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        let attribute = match canonical_field("macos", by) {
+            Some(attribute) => attribute,
+            None => return format!("cannot search by {}, expected target, service or user", by),
+        };
+        let class = match item_class(options) {
+            ItemClassKind::GenericPassword => "generic passwords",
+            ItemClassKind::InternetPassword => "internet passwords",
+            ItemClassKind::Certificate => "certificates",
+            ItemClassKind::Key => "keys",
+            ItemClassKind::Identity => "identities",
+        };
+
+        let mut explanation = format!(
+            "queries the keychain for {} whose {} is \"{}\", ignoring case",
+            class, attribute, query
+        );
+        if let Limit::Max(max) = options.limit {
+            explanation.push_str(&format!(", keeping at most {}", max));
+        }
+        if let Some(protocol) = &options.protocol {
+            explanation.push_str(&format!(", with the ptcl for {}", protocol));
+        }
+        if let Some(auth_type) = &options.auth_type {
+            explanation.push_str(&format!(", with the atyp for {}", auth_type));
+        }
+        match &options.keychain {
+            Some(Keychain::Login) => explanation.push_str(", in the login keychain"),
+            Some(Keychain::System) => explanation.push_str(", in the System keychain"),
+            Some(Keychain::Common) => explanation.push_str(", in the common keychain"),
+            Some(Keychain::Path(path)) => {
+                explanation.push_str(&format!(", in the keychain at {}", path))
+            }
+            None => {}
+        }
+        explanation
+    }
+}
+// Protocol names and the four character codes the keychain stores in `ptcl`.
+const PROTOCOLS: &[(&str, &str)] = &[
+    ("ftp", "ftp "),
+    ("ftps", "ftps"),
+    ("http", "http"),
+    ("https", "htps"),
+    ("imap", "imap"),
+    ("imaps", "imps"),
+    ("ldap", "ldap"),
+    ("ldaps", "ldps"),
+    ("pop3", "pop3"),
+    ("pop3s", "pops"),
+    ("smb", "smb "),
+    ("smtp", "smtp"),
+    ("ssh", "ssh "),
+    ("svn", "svn "),
+    ("telnet", "teln"),
+];
+
+// Authentication type names and the four character codes stored in `atyp`.
+const AUTH_TYPES: &[(&str, &str)] = &[
+    ("default", "dflt"),
+    ("dpa", "dpaa"),
+    ("html_form", "form"),
+    ("http_basic", "http"),
+    ("http_digest", "httd"),
+    ("msn", "msna"),
+    ("ntlm", "ntlm"),
+    ("rpa", "rpaa"),
+];
+
+// Whether an item's attribute holds the code for the name, or the code
+// itself when the name isn't known. Codes are space padded, so compare
+// them trimmed.
+fn attribute_matches(
+    item: &HashMap<String, String>,
+    key: &str,
+    name: &str,
+    codes: &[(&str, &str)],
+) -> bool {
+    let code = codes
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, code)| code);
+
+    item.get(key).map_or(false, |value| {
+        value.trim().eq_ignore_ascii_case(code.trim())
+    })
+}
+
+// The class searched, internet passwords in place of generic
+// ones when matching on the protocol or authentication type.
+fn item_class(options: &SearchOptions) -> ItemClassKind {
+    let internet = options.protocol.is_some() || options.auth_type.is_some();
+    match options.item_class {
+        ItemClassKind::GenericPassword if internet => ItemClassKind::InternetPassword,
+        item_class => item_class,
+    }
+}
+
+// Not wrapped by security-framework, which already links the Security framework.
+extern "C" {
+    fn SecKeychainGetStatus(keychain: CFTypeRef, status: *mut u32) -> i32;
+}
+
+// The status bit set while a keychain is unlocked, kSecUnlockStateStatus.
+const UNLOCK_STATE_STATUS: u32 = 1;
+
+// Opens the default keychain and reads its status, naming it locked
+// when it is.
+fn health_check() -> BackendHealth {
+    let keychain = match SecKeychain::default() {
+        Ok(keychain) => keychain,
+        Err(err) => return BackendHealth::new("macos", Some(err.to_string())),
+    };
+    let mut status = 0;
+    let code = unsafe { SecKeychainGetStatus(keychain.as_CFTypeRef(), &mut status) };
+    if code != 0 {
+        return BackendHealth::new(
+            "macos",
+            Some(security_framework::base::Error::from_code(code).to_string()),
+        );
+    }
+
+    let mut health = BackendHealth::new("macos", None);
+    if status & UNLOCK_STATE_STATUS == 0 {
+        health.locked.push("default".to_string());
+    }
+    health
 }
-// Type matching for search types.
+
+// Opens the keychain a search is scoped to.
+fn open_keychain(keychain: &Keychain) -> Result<SecKeychain> {
+    let keychain = match keychain {
+        Keychain::Login => SecKeychain::default_for_domain(SecPreferencesDomain::User),
+        Keychain::System => SecKeychain::default_for_domain(SecPreferencesDomain::System),
+        Keychain::Common => SecKeychain::default_for_domain(SecPreferencesDomain::Common),
+        Keychain::Path(path) => SecKeychain::open(path),
+    };
+
+    keychain.map_err(|err| ErrorCode::SearchError(format!("Couldn't open keychain: {}", err)))
+}
+
+// Type matching for search types, and the other attributes a
+// native query can set.
 enum MacSearchType {
     Label,
     Service,
     Account,
+    AccessGroup,
+    Subject,
 }
 // Perform search, returns a CredentialSearchResult.
-fn search(by: &str, query: &str) -> CredentialSearchResult {
-    let mut count = 0;
-    let mut new_search = item::ItemSearchOptions::new();
-
-    let search_default = &mut new_search
-        .class(item::ItemClass::generic_password())
-        .limit(item::Limit::All)
-        .load_attributes(true)
-        .case_insensitive(Some(true));
-
+fn search(by: &str, query: &str, options: &SearchOptions) -> CredentialSearchResult {
     let by = match by.to_ascii_lowercase().as_str() {
         "target" => MacSearchType::Label,
         "service" => MacSearchType::Service,
@@ -48,11 +248,99 @@ fn search(by: &str, query: &str) -> CredentialSearchResult {
         }
     };
 
-    let search = match by {
-        MacSearchType::Label => search_default.label(query).search(),
-        MacSearchType::Service => search_default.service(query).search(),
-        MacSearchType::Account => search_default.account(query).search(),
+    query_keychain(&[(by, query)], options)
+}
+
+// Sets each parameter with the ItemSearchOptions setter its key names,
+// by the setter or the attribute's four character code.
+fn native_query(
+    params: &HashMap<String, String>,
+    options: &SearchOptions,
+) -> CredentialSearchResult {
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort_unstable();
+
+    let mut attributes = Vec::new();
+    for key in keys {
+        let attribute = match key.as_str() {
+            "label" | "labl" => MacSearchType::Label,
+            "service" | "svce" => MacSearchType::Service,
+            "account" | "acct" => MacSearchType::Account,
+            "access_group" | "agrp" => MacSearchType::AccessGroup,
+            "subject" | "subj" => MacSearchType::Subject,
+            _ => return Err(ErrorCode::UnsupportedField(key.to_string())),
+        };
+        attributes.push((attribute, params[key].as_str()));
+    }
+
+    query_keychain(&attributes, options)
+}
+
+// Queries the keychain for the items with every given attribute.
+fn query_keychain(
+    attributes: &[(MacSearchType, &str)],
+    options: &SearchOptions,
+) -> CredentialSearchResult {
+    let mut count = 0;
+    let mut new_search = item::ItemSearchOptions::new();
+
+    // security-framework has no setters for the protocol or authentication
+    // type, so internet passwords are matched on them after the query.
+    let filtered = options.protocol.is_some() || options.auth_type.is_some();
+    let item_class = item_class(options);
+    let class = match item_class {
+        ItemClassKind::GenericPassword => item::ItemClass::generic_password(),
+        ItemClassKind::InternetPassword => item::ItemClass::internet_password(),
+        ItemClassKind::Certificate => item::ItemClass::certificate(),
+        ItemClassKind::Key => item::ItemClass::key(),
+        ItemClassKind::Identity => item::ItemClass::identity(),
+    };
+
+    // Only a maximum count can be pushed into the query, and only
+    // when every item it returns is kept.
+    let max = match options.limit {
+        Limit::Max(max) => Some(max),
+        Limit::All | Limit::Bytes(_) => None,
     };
+    let limit = match max {
+        Some(max) if !filtered => item::Limit::Max(i64::try_from(max).unwrap_or(i64::MAX)),
+        _ => item::Limit::All,
+    };
+
+    if let Some(keychain) = &options.keychain {
+        new_search.keychains(&[open_keychain(keychain)?]);
+    }
+
+    let search_default = &mut new_search
+        .class(class)
+        .limit(limit)
+        .load_attributes(true)
+        .case_insensitive(Some(true));
+
+    // Only passwords have a service and account.
+    let password = matches!(
+        item_class,
+        ItemClassKind::GenericPassword | ItemClassKind::InternetPassword
+    );
+    let by_password = attributes
+        .iter()
+        .any(|(attribute, _)| matches!(attribute, MacSearchType::Service | MacSearchType::Account));
+    if !password && by_password {
+        return Err(ErrorCode::SearchError(
+            "Certificates, keys and identities can only be searched by target".to_string(),
+        ));
+    }
+
+    for (attribute, value) in attributes {
+        match attribute {
+            MacSearchType::Label => search_default.label(value),
+            MacSearchType::Service => search_default.service(value),
+            MacSearchType::Account => search_default.account(value),
+            MacSearchType::AccessGroup => search_default.access_group(value),
+            MacSearchType::Subject => search_default.subject(value),
+        };
+    }
+    let search = search_default.search();
 
     let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
 
@@ -62,39 +350,127 @@ fn search(by: &str, query: &str) -> CredentialSearchResult {
     };
 
     for item in results {
-        count += 1;
-        match to_credential_search_result(item.simplify_dict(), &mut outer_map, count) {
-            Ok(_) => {}
-            Err(err) => return Err(err),
+        // Items whose attributes can't be simplified are skipped,
+        // rather than hiding every other result.
+        let result = match options.full_attributes {
+            true => full_dict(&item),
+            false => item.simplify_dict(),
+        };
+        let result = match result {
+            Some(map) => map,
+            None => continue,
+        };
+
+        if let Some(protocol) = &options.protocol {
+            if !attribute_matches(&result, "ptcl", protocol, PROTOCOLS) {
+                continue;
+            }
+        }
+        if let Some(auth_type) = &options.auth_type {
+            if !attribute_matches(&result, "atyp", auth_type, AUTH_TYPES) {
+                continue;
+            }
+        }
+        if max.map_or(false, |max| count >= max) {
+            break;
         }
+
+        count += 1;
+        outer_map.insert(count.to_string(), result);
+    }
+
+    if outer_map.is_empty() {
+        return Err(ErrorCode::NoResults);
     }
 
     Ok(outer_map)
 }
 
-// The returned item from search is converted to CredentialSearchResult type.
-// If none, a SearchError is returned for no items found. If results found, the "labl"
-// key is removed and placed in the outer map's key to differentiate between results.
-fn to_credential_search_result(
-    item: Option<HashMap<String, String>>,
-    outer_map: &mut HashMap<String, HashMap<String, String>>,
-    count: u32,
-) -> Result<()> {
-    let result = match item {
-        None => return Err(ErrorCode::NoResults),
-        Some(map) => map,
+// Every attribute of the item, converting the values simplify_dict
+// can't: numbers and booleans as text, data as base64 and dates as
+// ISO 8601 in UTC.
+fn full_dict(item: &item::SearchResult) -> Option<HashMap<String, String>> {
+    let dict = match item {
+        item::SearchResult::Dict(dict) => dict,
+        _ => return None,
     };
 
-    outer_map.insert(count.to_string(), result);
+    let (keys, values) = dict.get_keys_and_values();
+    let attributes = keys
+        .into_iter()
+        .zip(values)
+        .map(|(key, value)| {
+            let key = unsafe { CFString::wrap_under_get_rule(key as CFStringRef) };
+            let value = unsafe { CFType::wrap_under_get_rule(value as CFTypeRef) };
+            (key.to_string(), attribute_string(&value))
+        })
+        .collect();
+    Some(attributes)
+}
+
+fn attribute_string(value: &CFType) -> String {
+    if let Some(string) = value.downcast::<CFString>() {
+        string.to_string()
+    } else if let Some(boolean) = value.downcast::<CFBoolean>() {
+        bool::from(boolean).to_string()
+    } else if let Some(number) = value.downcast::<CFNumber>() {
+        match (number.to_i64(), number.to_f64()) {
+            (Some(number), _) => number.to_string(),
+            (None, Some(number)) => number.to_string(),
+            (None, None) => "unknown".to_string(),
+        }
+    } else if let Some(data) = value.downcast::<CFData>() {
+        base64(data.bytes())
+    } else if let Some(date) = value.downcast::<CFDate>() {
+        iso_8601(date.abs_time())
+    } else {
+        "unknown".to_string()
+    }
+}
 
-    Ok(())
+// Seconds from the Unix epoch to Core Foundation's, 2001-01-01.
+const CF_EPOCH_OFFSET: i64 = 978_307_200;
+
+// Formats a Core Foundation time as ISO 8601 in UTC, to the second.
+fn iso_8601(time: CFAbsoluteTime) -> String {
+    let seconds = time.floor() as i64 + CF_EPOCH_OFFSET;
+    let (days, seconds) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // The civil date of a day count, after Howard Hinnant's days_from_civil.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
 
-    use crate::{tests::generate_random_string, Error, Limit, List, Search};
+    use crate::search::DEFAULT_HIDDEN_FIELDS;
+    use crate::{
+        tests::generate_random_string, Error, ItemClassKind, Keychain, Limit, List, Search,
+    };
     use core_foundation::{
         base::{CFGetTypeID, CFTypeRef, TCFType, TCFTypeRef},
         date::{CFDate, CFDateRef},
@@ -107,6 +483,7 @@ mod tests {
     use security_framework_sys::{
         base::errSecSuccess,
         item::{kSecReturnAttributes, kSecValueRef},
+        keychain::{SecAuthenticationType, SecProtocolType},
     };
 
     fn get_keychain() -> SecKeychain {
@@ -151,6 +528,7 @@ mod tests {
 
         let keychain = get_keychain();
         let mut expected = String::new();
+        let mut native_fields = HashMap::new();
         let item = &keychain
             .find_generic_password(&name, &name)
             .expect("Error finding test credential")
@@ -191,7 +569,7 @@ mod tests {
             }
             let (keys, values) = attributes.get_keys_and_values();
 
-            for (key, value) in keys.into_iter().zip(values.into_iter()) {
+            for (key, value) in keys.into_iter().zip(values) {
                 let key_str =
                     unsafe { CFString::wrap_under_get_rule(key as CFStringRef).to_string() };
 
@@ -221,14 +599,22 @@ mod tests {
                     }
                     _ => "Error getting type ID".to_string(),
                 };
-                if key_str == "crtr".to_string() {
+                if DEFAULT_HIDDEN_FIELDS.contains(&key_str.as_str()) {
+                    continue;
+                }
+                if key_str == "crtr" {
                     expected.push_str(format!("{}: unknown\n", key_str).as_str());
                 } else {
                     expected.push_str(format!("{}: {}\n", key_str, value_str).as_str());
                 }
                 expected.push_str(format!("{}\n", &result_count.to_string()).as_str());
+                native_fields.insert(key_str, value_str);
             }
         }
+        for (field, native) in [("service", "svce"), ("user", "acct"), ("target", "labl")] {
+            let value = native_fields.get(native).cloned().unwrap_or_default();
+            expected.push_str(format!("{}: {}\n", field, value).as_str());
+        }
 
         let actual_set: HashSet<&str> = list_result.lines().collect();
         let expected_set: HashSet<&str> = expected.lines().collect();
@@ -270,15 +656,13 @@ mod tests {
             .by_user("test-user");
         let list = List::list_credentials(&search, Limit::Max(1));
 
-        let lines = list.lines().count();
-
         // Because the list is one large string concatenating
         // credentials together, to test the return to only be
-        // one credential, we count the amount of lines returned.
-        // To adjust this test: add extra random names, create
-        // more credentials with test-user, adjust the limit and
-        // make the assert number a multiple of 6.
-        assert_eq!(8, lines);
+        // one credential, we count the ID lines, the only ones
+        // that aren't `key: value` fields.
+        let credentials = list.lines().filter(|line| !line.contains(": ")).count();
+
+        assert_eq!(1, credentials);
 
         delete_credential(&name1, Some("test-user"));
         delete_credential(&name2, Some("test-user"));
@@ -286,6 +670,130 @@ mod tests {
         delete_credential(&name4, Some("test-user"));
     }
 
+    #[test]
+    fn test_search_limit() {
+        let user = generate_random_string();
+        let name1 = generate_random_string();
+        let name2 = generate_random_string();
+
+        create_credential(&name1, Some(&user));
+        create_credential(&name2, Some(&user));
+
+        let result = Search::new()
+            .expect("Error creating test-search-limit search")
+            .with_limit(Limit::Max(1))
+            .by_user(&user)
+            .expect("Failed to search with limit");
+
+        assert_eq!(1, result.len());
+
+        delete_credential(&name1, Some(&user));
+        delete_credential(&name2, Some(&user));
+    }
+
+    #[test]
+    fn test_search_protocol() {
+        let keychain = get_keychain();
+        let server = format!("{}.example.com", generate_random_string());
+        let user = generate_random_string();
+        for protocol in [SecProtocolType::HTTPS, SecProtocolType::FTP] {
+            keychain
+                .set_internet_password(
+                    &server,
+                    None,
+                    &user,
+                    "",
+                    None,
+                    protocol,
+                    SecAuthenticationType::Default,
+                    "test-password".as_bytes(),
+                )
+                .expect("Error creating test internet password");
+        }
+
+        let result = Search::new()
+            .expect("Error creating test-search-protocol search")
+            .with_protocol("https")
+            .by_user(&user)
+            .expect("Failed to search by protocol");
+
+        for protocol in [SecProtocolType::HTTPS, SecProtocolType::FTP] {
+            let (_password, item) = keychain
+                .find_internet_password(
+                    &server,
+                    None,
+                    &user,
+                    "",
+                    None,
+                    protocol,
+                    SecAuthenticationType::Default,
+                )
+                .expect("Error getting test internet password");
+            item.delete();
+        }
+
+        assert_eq!(1, result.len());
+        let inner_map = result.values().next().expect("No result");
+        assert_eq!(Some("htps"), inner_map.get("ptcl").map(String::as_str));
+    }
+
+    #[test]
+    fn test_in_keychain() {
+        let name = generate_random_string();
+        create_credential(&name, None);
+
+        let login = Search::new()
+            .expect("Error creating test-in-keychain search")
+            .in_keychain(Keychain::Login)
+            .by_service(&name);
+        let system = Search::new()
+            .expect("Error creating test-in-keychain search")
+            .in_keychain(Keychain::System)
+            .by_service(&name);
+
+        delete_credential(&name, None);
+        assert_eq!(
+            1,
+            login.expect("Credential not found in login keychain").len()
+        );
+        assert!(matches!(system, Err(Error::NoResults)));
+    }
+
+    #[test]
+    fn test_iso_8601() {
+        assert_eq!("2001-01-01T00:00:00Z", super::iso_8601(0.0));
+        assert_eq!("1970-01-01T00:00:00Z", super::iso_8601(-978_307_200.0));
+        assert_eq!("2024-02-29T12:34:56Z", super::iso_8601(730_902_896.5));
+        assert_eq!("2000-12-31T23:59:59Z", super::iso_8601(-0.5));
+    }
+
+    #[test]
+    fn test_full_attributes() {
+        let name = generate_random_string();
+        create_credential(&name, None);
+
+        let result = Search::new()
+            .expect("Error creating test-full-attributes search")
+            .full_attributes(true)
+            .by_service(&name);
+
+        delete_credential(&name, None);
+        let result = result.expect("Failed to search with full attributes");
+        let inner = &result["1"];
+        assert!(inner.values().all(|value| value != "unknown"));
+        assert!(inner.get("mdat").map_or(false, |date| date.ends_with('Z')));
+    }
+
+    #[test]
+    fn test_item_class_by_target_only() {
+        let result = Search::new()
+            .expect("Error creating test-item-class search")
+            .item_class(ItemClassKind::Certificate)
+            .by_user(&generate_random_string());
+
+        assert!(matches!(result, Err(Error::SearchError(_))));
+    }
+
     #[test]
     fn no_results() {
         let name = generate_random_string();
@@ -16,22 +16,92 @@ The mock module creates a default credential store that can be searched with the
 keyring-search API.
  */
 
-use regex::Regex;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
-use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard};
 
-use super::error::Error as ErrorCode;
-use super::search::{CredentialSearch, CredentialSearchApi, CredentialSearchResult};
+use super::error::{Error as ErrorCode, Result};
+use super::search::{
+    canonical_field, filter_native, CredentialSearch, CredentialSearchApi, CredentialSearchResult,
+    CredentialSearchResultWithWarnings, MatchMode, SearchOptions, SearchPlan, ValueMatch,
+};
 
 lazy_static::lazy_static! {
     static ref GLOBAL_CREDENTIAL_STORE: MockCredentialStore<MockData> = MockCredentialStore::default();
+    // Held by every TestGuard, so scoped tests run one at a time.
+    static ref TEST_SCOPE_LOCK: Mutex<()> = Mutex::new(());
 }
 
 pub fn get_store() -> &'static MockCredentialStore<MockData> {
     &GLOBAL_CREDENTIAL_STORE
 }
 
+thread_local! {
+    // The error the next mock search on this thread fails with.
+    static NEXT_ERROR: RefCell<Option<ErrorCode>> = const { RefCell::new(None) };
+}
+
+/// Makes the next mock search on this thread fail with the error, to
+/// test how code handles a failing store, such as a
+/// [Connection](ErrorCode::Connection) error.
+///
+/// The error is returned once, then searches run as usual again.
+/// It is kept per thread, so tests running in parallel don't fail
+/// each other's searches.
+///
+/// # Example
+///     use keyring_search::{mock, Error, Search};
+///     mock::set_next_error(Error::Connection("daemon stopped".to_string()));
+///     let search = keyring_search::set_default_credential_search(mock::default_credential_search())
+///         .unwrap();
+///     assert!(matches!(search.by_user("alice"), Err(Error::Connection(_))));
+pub fn set_next_error(error: ErrorCode) {
+    NEXT_ERROR.with(|next| *next.borrow_mut() = Some(error));
+}
+
+// Fails with the error set by set_next_error, clearing it.
+fn take_next_error() -> Result<()> {
+    match NEXT_ERROR.with(|next| next.borrow_mut().take()) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Starts a test with the global mock store to itself.
+///
+/// The returned guard empties the store, then puts back what it held
+/// when dropped, even if the test panics. Scoped tests wait for each
+/// other, so only tests that don't take a scope can still see each
+/// other's credentials, and have theirs cleared.
+///
+/// # Example
+///     use keyring_search::mock::{self, CredentialStore, MockData};
+///     let _guard = mock::test_scope();
+///     mock::get_store().add(MockData::builder().user("alice").build());
+///     assert_eq!(1, mock::get_store().get().len());
+pub fn test_scope() -> TestGuard<MockData> {
+    get_store().scope()
+}
+
+/// Restores a [MockCredentialStore] when dropped, see [test_scope].
+pub struct TestGuard<T: CredentialFields + 'static> {
+    store: &'static MockCredentialStore<T>,
+    snapshot: Vec<Arc<T>>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl<T: CredentialFields + 'static> Drop for TestGuard<T> {
+    fn drop(&mut self) {
+        let mut store = self
+            .store
+            .inner
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+        *store = std::mem::take(&mut self.snapshot);
+    }
+}
+
 pub trait CredentialFields {
     fn service(&self) -> String;
     fn target(&self) -> String;
@@ -40,7 +110,7 @@ pub trait CredentialFields {
 
 pub trait CredentialStore<T> {
     fn add(&self, credential: T);
-    fn get(&self) -> RwLockReadGuard<Vec<Arc<T>>>;
+    fn get(&self) -> RwLockReadGuard<'_, Vec<Arc<T>>>;
 }
 
 #[derive(Debug)]
@@ -56,6 +126,22 @@ impl<T: CredentialFields> Default for MockCredentialStore<T> {
     }
 }
 
+impl<T: CredentialFields + 'static> MockCredentialStore<T> {
+    /// Empties the store until the returned guard is dropped, as [test_scope] does.
+    pub fn scope(&'static self) -> TestGuard<T> {
+        // A test that panicked in its scope has already been restored.
+        let lock = TEST_SCOPE_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let mut store = self.inner.write().unwrap_or_else(|err| err.into_inner());
+        TestGuard {
+            store: self,
+            snapshot: std::mem::take(&mut *store),
+            _lock: lock,
+        }
+    }
+}
+
 impl<T: CredentialFields> CredentialStore<T> for MockCredentialStore<T> {
     fn add(&self, credential: T) {
         let mut store = self
@@ -64,18 +150,65 @@ impl<T: CredentialFields> CredentialStore<T> for MockCredentialStore<T> {
             .expect("Rwlock poisoned in MockCredentialStore add method");
         store.push(Arc::new(credential))
     }
-    fn get(&self) -> RwLockReadGuard<Vec<Arc<T>>> {
+    fn get(&self) -> RwLockReadGuard<'_, Vec<Arc<T>>> {
         self.inner
             .read()
             .expect("Rwlock poisoned in MockCredentialStore get method")
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct MockData {
     pub service: String,
     pub target: String,
     pub user: String,
+    /// Platform specific fields, such as a label or comment,
+    /// returned in search results alongside the core three.
+    pub extra: HashMap<String, String>,
+}
+
+impl MockData {
+    /// Starts a [builder](MockDataBuilder), with every field empty.
+    pub fn builder() -> MockDataBuilder {
+        MockDataBuilder::default()
+    }
+}
+
+/// Builds [MockData], for mock credentials with extra fields.
+///
+/// # Example
+///     use keyring_search::mock::MockData;
+///     let data = MockData::builder()
+///         .service("aws")
+///         .user("alice")
+///         .extra("comment", "work account")
+///         .build();
+#[derive(Debug, Default)]
+pub struct MockDataBuilder {
+    data: MockData,
+}
+
+impl MockDataBuilder {
+    pub fn service(mut self, service: &str) -> MockDataBuilder {
+        self.data.service = service.to_string();
+        self
+    }
+    pub fn target(mut self, target: &str) -> MockDataBuilder {
+        self.data.target = target.to_string();
+        self
+    }
+    pub fn user(mut self, user: &str) -> MockDataBuilder {
+        self.data.user = user.to_string();
+        self
+    }
+    /// Adds an extra field, replacing any earlier value for the key.
+    pub fn extra(mut self, key: &str, value: &str) -> MockDataBuilder {
+        self.data.extra.insert(key.to_string(), value.to_string());
+        self
+    }
+    pub fn build(self) -> MockData {
+        self.data
+    }
 }
 
 impl CredentialFields for MockData {
@@ -94,22 +227,73 @@ pub struct MockCredentialSearch {}
 
 impl CredentialSearchApi for MockCredentialSearch {
     fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
-        let re = format!(r#"(?i){}"#, query);
-        let regex = match Regex::new(re.as_str()) {
-            Ok(regex) => regex,
-            Err(err) => return Err(ErrorCode::SearchError(format!("Regex Error, {}", err))),
-        };
+        self.by_with_options(by, query, &SearchOptions::default())
+            .map(|result| result.results)
+    }
 
-        match by.to_ascii_lowercase().as_str() {
-            "user" => search_by_user(regex),
-            "service" => search_by_service(regex),
-            "target" => search_by_target(regex),
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        take_next_error()?;
+        let value_match = ValueMatch::regex_or_tokens(query, options)?;
+        let max = options.limit.max_items();
+
+        let results = match by.to_ascii_lowercase().as_str() {
+            "user" => search_by_user(value_match, max),
+            "service" => search_by_service(value_match, max),
+            "target" => search_by_target(value_match, max),
             _ => Err(ErrorCode::Unexpected("Mock by parameter".to_string())),
-        }
+        }?;
+
+        Ok(CredentialSearchResultWithWarnings {
+            results,
+            ..Default::default()
+        })
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        let field = match canonical_field("mock", by) {
+            Some(field) => field,
+            None => return format!("cannot search by {}, expected user, service or target", by),
+        };
+
+        format!(
+            "keeps the mock credentials whose {} {}",
+            field,
+            ValueMatch::describe_regex_or_tokens(query, options)
+        )
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        SearchPlan::new(self, by, query, options, "the mock store")
+    }
+
+    fn all(&self, options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        // An empty regex matches every target, whatever the match mode.
+        let options = SearchOptions {
+            match_mode: MatchMode::Regex,
+            ..options.clone()
+        };
+        self.by_with_options("target", "", &options)
+    }
+
+    fn native_query(
+        &self,
+        params: &HashMap<String, String>,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        filter_native(self, params, &["User", "Service", "Target"], options)
     }
 }
 
-fn search_by_user(regex: Regex) -> CredentialSearchResult {
+fn search_by_user(value_match: ValueMatch, max: usize) -> CredentialSearchResult {
     let store = get_store();
     let data = match store.inner.write() {
         Ok(data) => data,
@@ -123,20 +307,16 @@ fn search_by_user(regex: Regex) -> CredentialSearchResult {
     let mut results = Vec::new();
 
     let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
-    let mut inner_map: HashMap<String, String> = HashMap::new();
 
     for credential in data.iter() {
-        if regex.is_match(&credential.user()) {
+        if value_match.is_match(&credential.user()) {
             results.push(credential);
         }
     }
 
-    for result in results {
+    for result in results.into_iter().take(max) {
         count += 1;
-        inner_map.insert("User".to_string(), result.user.clone());
-        inner_map.insert("Service".to_string(), result.service.clone());
-        inner_map.insert("Target".to_string(), result.target.clone());
-        outer_map.insert(count.to_string(), inner_map.clone());
+        outer_map.insert(count.to_string(), credential_map(result));
     }
 
     if count == 0 {
@@ -145,7 +325,7 @@ fn search_by_user(regex: Regex) -> CredentialSearchResult {
 
     Ok(outer_map)
 }
-fn search_by_service(regex: Regex) -> CredentialSearchResult {
+fn search_by_service(value_match: ValueMatch, max: usize) -> CredentialSearchResult {
     let store = get_store();
     let data = match store.inner.write() {
         Ok(data) => data,
@@ -159,20 +339,16 @@ fn search_by_service(regex: Regex) -> CredentialSearchResult {
     let mut results = Vec::new();
 
     let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
-    let mut inner_map: HashMap<String, String> = HashMap::new();
 
     for credential in data.iter() {
-        if regex.is_match(&credential.service()) {
+        if value_match.is_match(&credential.service()) {
             results.push(credential);
         }
     }
 
-    for result in results {
+    for result in results.into_iter().take(max) {
         count += 1;
-        inner_map.insert("User".to_string(), result.user.clone());
-        inner_map.insert("Service".to_string(), result.service.clone());
-        inner_map.insert("Target".to_string(), result.target.clone());
-        outer_map.insert(count.to_string(), inner_map.clone());
+        outer_map.insert(count.to_string(), credential_map(result));
     }
 
     if count == 0 {
@@ -181,7 +357,7 @@ fn search_by_service(regex: Regex) -> CredentialSearchResult {
 
     Ok(outer_map)
 }
-fn search_by_target(regex: Regex) -> CredentialSearchResult {
+fn search_by_target(value_match: ValueMatch, max: usize) -> CredentialSearchResult {
     let store = get_store();
     let data = match store.inner.write() {
         Ok(data) => data,
@@ -195,20 +371,16 @@ fn search_by_target(regex: Regex) -> CredentialSearchResult {
     let mut results = Vec::new();
 
     let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
-    let mut inner_map: HashMap<String, String> = HashMap::new();
 
     for credential in data.iter() {
-        if regex.is_match(&credential.target()) {
+        if value_match.is_match(&credential.target()) {
             results.push(credential);
         }
     }
 
-    for result in results {
+    for result in results.into_iter().take(max) {
         count += 1;
-        inner_map.insert("User".to_string(), result.user.clone());
-        inner_map.insert("Service".to_string(), result.service.clone());
-        inner_map.insert("Target".to_string(), result.target.clone());
-        outer_map.insert(count.to_string(), inner_map.clone());
+        outer_map.insert(count.to_string(), credential_map(result));
     }
 
     if count == 0 {
@@ -218,17 +390,33 @@ fn search_by_target(regex: Regex) -> CredentialSearchResult {
     Ok(outer_map)
 }
 
+// Builds a result's inner map, the extra fields followed by the core three.
+fn credential_map(credential: &MockData) -> HashMap<String, String> {
+    let mut inner_map = credential.extra.clone();
+    inner_map.insert("User".to_string(), credential.user.clone());
+    inner_map.insert("Service".to_string(), credential.service.clone());
+    inner_map.insert("Target".to_string(), credential.target.clone());
+    inner_map
+}
+
 pub fn default_credential_search() -> Box<CredentialSearch> {
     Box::new(MockCredentialSearch {})
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_store, MockData};
+    use super::{get_store, MockCredentialStore, MockData};
     use crate::mock::CredentialStore;
-    use crate::tests::generate_random_string;
-    use crate::{mock, set_default_credential_search, Error, Limit, List};
-    use std::collections::HashSet;
+    use crate::search::{
+        CredentialSearchResult, ExactMatcher, GlobMatcher, MATCHED_QUERY_FIELD, TIER_FIELD,
+    };
+    use crate::tests::{generate_random_string, mock_search};
+    use crate::{
+        mock, CredentialSearchResultExt, Error, FromSearchResult, Limit, List, MatchMode,
+        MatchTier, RegexFlags, SearchField, SearchResult,
+    };
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
 
     fn searchable_entries() -> [String; 4] {
         let store = get_store();
@@ -242,24 +430,28 @@ mod tests {
             service: name1.clone(),
             target: name1.clone(),
             user: name1.clone(),
+            extra: HashMap::new(),
         };
 
         let data2 = MockData {
             service: name2.clone(),
             target: name2.clone(),
             user: name2.clone(),
+            extra: HashMap::new(),
         };
 
         let data3 = MockData {
             service: name3.clone(),
             target: name3.clone(),
             user: name3.clone(),
+            extra: HashMap::new(),
         };
 
         let data4 = MockData {
             service: name4.clone(),
             target: name4.clone(),
             user: name4.clone(),
+            extra: HashMap::new(),
         };
 
         store.add(data1);
@@ -272,16 +464,15 @@ mod tests {
 
     #[test]
     fn test_mock_search_by_user() {
+        let _g = mock::test_scope();
         let names = searchable_entries();
-        let result = set_default_credential_search(mock::default_credential_search())
-            .expect("Failed to create mock search")
-            .by_user(&names[1]);
+        let result = mock_search().by_user(&names[1]);
 
         let list = List::list_credentials(&result, Limit::All);
 
         let expected_str = format!(
-            "1\nTarget: {}\nService: {}\nUser: {}\n",
-            &names[1], &names[1], &names[1]
+            "1\nTarget: {0}\nService: {0}\nUser: {0}\ntarget: {0}\nservice: {0}\nuser: {0}\n",
+            &names[1]
         );
 
         let expected_set: HashSet<&str> = expected_str.lines().collect();
@@ -295,16 +486,15 @@ mod tests {
 
     #[test]
     fn test_mock_search_by_target() {
+        let _g = mock::test_scope();
         let names = searchable_entries();
-        let result = set_default_credential_search(mock::default_credential_search())
-            .expect("Failed to create mock search")
-            .by_target(&names[1]);
+        let result = mock_search().by_target(&names[1]);
 
         let list = List::list_credentials(&result, Limit::All);
 
         let expected_str = format!(
-            "1\nTarget: {}\nService: {}\nUser: {}\n",
-            &names[1], &names[1], &names[1]
+            "1\nTarget: {0}\nService: {0}\nUser: {0}\ntarget: {0}\nservice: {0}\nuser: {0}\n",
+            &names[1]
         );
 
         let expected_set: HashSet<&str> = expected_str.lines().collect();
@@ -318,16 +508,15 @@ mod tests {
 
     #[test]
     fn test_mock_search_by_service() {
+        let _g = mock::test_scope();
         let names = searchable_entries();
-        let result = set_default_credential_search(mock::default_credential_search())
-            .expect("Failed to create mock search")
-            .by_service(&names[1]);
+        let result = mock_search().by_service(&names[1]);
 
         let list = List::list_credentials(&result, Limit::All);
 
         let expected_str = format!(
-            "1\nTarget: {}\nService: {}\nUser: {}\n",
-            &names[1], &names[1], &names[1]
+            "1\nTarget: {0}\nService: {0}\nUser: {0}\ntarget: {0}\nservice: {0}\nuser: {0}\n",
+            &names[1]
         );
 
         let expected_set: HashSet<&str> = expected_str.lines().collect();
@@ -340,48 +529,1095 @@ mod tests {
     }
 
     #[test]
-    fn no_results() {
+    fn test_mock_search_verbose() {
+        let _g = mock::test_scope();
+        let names = searchable_entries();
+        let result = mock_search()
+            .by_verbose("user", &names[2])
+            .expect("Failed to search by user");
+
+        assert_eq!(1, result.results.len());
+        assert!(result.warnings.is_empty(), "Mock search returned warnings");
+    }
+
+    #[test]
+    fn test_mock_search_observer() {
+        let _g = mock::test_scope();
+        let names = searchable_entries();
+        let timings = Arc::new(Mutex::new(Vec::new()));
+        let observed = timings.clone();
+        mock_search()
+            .with_observer(move |timing| observed.lock().unwrap().push(timing))
+            .by_service(&names[0])
+            .expect("Failed to search by service");
+
+        let timings = timings.lock().unwrap();
+        assert_eq!(1, timings.len());
+        assert_eq!("mock", timings[0].backend);
+        assert_eq!("service", timings[0].field);
+        assert_eq!(names[0].len(), timings[0].query_len);
+        assert_eq!(1, timings[0].result_count);
+    }
+
+    #[test]
+    fn test_mock_search_select() {
+        let _g = mock::test_scope();
+        let names = searchable_entries();
+        let result = mock_search()
+            .select(&["user", "service"])
+            .by_target(&names[3]);
+
+        let list = List::list_credentials(&result, Limit::All);
+
+        let expected = format!(
+            "1\nService: {0}\nUser: {0}\nservice: {0}\nuser: {0}\n",
+            &names[3]
+        );
+        assert_eq!(expected, list);
+    }
+
+    #[test]
+    fn test_mock_search_first() {
+        let _g = mock::test_scope();
+        let names = searchable_entries();
+        let search = mock_search();
+
+        let first = search
+            .first("user", &names[1])
+            .expect("Failed to search first")
+            .expect("No first result");
+        let none = search
+            .first("user", &generate_random_string())
+            .expect("Failed to search first");
+
+        assert_eq!("1", first.id());
+        assert_eq!(Some(names[1].as_str()), first.get("user"));
+        assert!(none.is_none());
+    }
+
+    #[test]
+    fn test_mock_search_group_by() {
+        let _g = mock::test_scope();
+        let names = searchable_entries();
+        let search = mock_search();
+
+        let groups = search
+            .group_by("user", &format!("{}|{}", names[0], names[1]), "service")
+            .expect("Failed to group results");
+
+        assert_eq!(2, groups.len());
+        assert_eq!(1, groups[&names[0]].len());
+        assert_eq!(Some(names[1].as_str()), groups[&names[1]][0].get("user"));
+    }
+
+    #[test]
+    fn test_mock_by_field() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(MockData::builder().service("mail").user(&name).build());
+        let search = mock_search();
+
+        for field in [SearchField::User, SearchField::Account] {
+            let result = search
+                .by_field(field, &name)
+                .expect("Failed to search by field");
+            assert_eq!(1, result.len());
+        }
+        assert!(matches!(
+            search.by_field(SearchField::Label, &name),
+            Err(Error::UnsupportedField(field)) if field == "label"
+        ));
+    }
+
+    #[test]
+    fn test_mock_into_results() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let store = get_store();
+        for user in ["alice", "bob"] {
+            store.add(
+                MockData::builder()
+                    .service("mail")
+                    .target(&name)
+                    .user(user)
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let results = search
+            .by_target(&name)
+            .into_results()
+            .expect("Failed to convert results");
+        let ids: Vec<u32> = results.iter().map(|entry| entry.id).collect();
+        assert_eq!(vec![1, 2], ids);
+        let users: Vec<&str> = results
+            .iter()
+            .map(|entry| entry.attributes["User"].as_str())
+            .collect();
+        assert_eq!(vec!["alice", "bob"], users);
+        assert_eq!(
+            Some("mail"),
+            results.entries()[0]
+                .attributes
+                .get("Service")
+                .map(String::as_str)
+        );
+
+        assert!(matches!(
+            search.by_target(&generate_random_string()).into_results(),
+            Err(Error::NoResults)
+        ));
+    }
+
+    #[test]
+    fn test_mock_search_tree() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let store = get_store();
+        for (service, user) in [("b", "bob"), ("a", "carol"), ("b", "alice"), ("b", "bob")] {
+            store.add(
+                MockData::builder()
+                    .service(service)
+                    .target(&name)
+                    .user(user)
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let tree = search.tree("target", &name).expect("Failed to build tree");
+        let services: Vec<&str> = tree.keys().map(String::as_str).collect();
+        assert_eq!(vec!["a", "b"], services);
+        let users: Vec<&str> = tree["b"].keys().map(String::as_str).collect();
+        assert_eq!(vec!["alice", "bob"], users);
+        assert_eq!(2, tree["b"]["bob"].len());
+        assert_eq!(1, tree["a"]["carol"].len());
+    }
+
+    #[test]
+    fn test_mock_search_ranked() {
+        let _g = mock::test_scope();
+        let prefix = generate_random_string();
+        let store = get_store();
+        for user in [
+            format!("x{}", prefix),
+            format!("{}x", prefix),
+            prefix.clone(),
+        ] {
+            store.add(
+                MockData::builder()
+                    .service(&user)
+                    .target(&format!("t-{}", user))
+                    .user(&user)
+                    .build(),
+            );
+        }
+
+        let search = mock_search().ranked(true);
+        let result = search.clone().select(&["user"]).by_user(&prefix);
+        let list = List::list_ranked(&result, Limit::All);
+
+        let users: Vec<&str> = list
+            .lines()
+            .filter_map(|line| line.strip_prefix("User: "))
+            .collect();
+        assert_eq!(
+            vec![
+                prefix.clone(),
+                format!("{}x", prefix),
+                format!("x{}", prefix)
+            ],
+            users
+        );
+
+        // The score is taken from the searched field even when it isn't selected.
+        let result = search.select(&["target"]).by_user(&prefix);
+        let list = List::list_ranked(&result, Limit::All);
+        let targets: Vec<&str> = list
+            .lines()
+            .filter_map(|line| line.strip_prefix("Target: "))
+            .collect();
+        assert_eq!(
+            vec![
+                format!("t-{}", prefix),
+                format!("t-{}x", prefix),
+                format!("t-x{}", prefix)
+            ],
+            targets
+        );
+    }
+
+    #[test]
+    fn test_mock_search_extra() {
+        let _g = mock::test_scope();
         let name = generate_random_string();
-        let result = set_default_credential_search(mock::default_credential_search())
-            .expect("Failed to create mock search")
+        get_store().add(
+            MockData::builder()
+                .service(&name)
+                .user(&name)
+                .extra("comment", "work account")
+                .build(),
+        );
+
+        let result = mock_search()
             .by_service(&name)
-            .unwrap_err();
+            .expect("Failed to search mock store");
+
+        let inner_map = &result["1"];
+        assert_eq!(
+            Some("work account"),
+            inner_map.get("comment").map(String::as_str)
+        );
+        assert_eq!(
+            Some(name.as_str()),
+            inner_map.get("User").map(String::as_str)
+        );
+        assert_eq!(Some(""), inner_map.get("Target").map(String::as_str));
+    }
+
+    #[test]
+    fn test_mock_query() {
+        let _g = mock::test_scope();
+        let user = generate_random_string();
+        let store = get_store();
+        for service in ["aws-prod", "aws-dev", "gcp"] {
+            store.add(
+                MockData::builder()
+                    .service(service)
+                    .target(&format!("{} target", service))
+                    .user(&user)
+                    .build(),
+            );
+        }
+
+        let search = mock_search();
+
+        let result = search
+            .query(&format!("user:{} service:AWS", user))
+            .expect("Failed to query mock store");
+        assert_eq!(2, result.len());
+
+        let result = search
+            .query(&format!(r#"user:{} "dev target""#, user))
+            .expect("Failed to query mock store");
+        assert_eq!(1, result.len());
+        assert_eq!(
+            Some("aws-dev"),
+            result
+                .values()
+                .next()
+                .and_then(|r| r.get("Service"))
+                .map(String::as_str)
+        );
+
+        assert!(matches!(
+            search.query(&format!("user:{} service:azure", user)),
+            Err(Error::NoResults)
+        ));
+
+        let result = search
+            .query(&format!("{} DEV", user))
+            .expect("Failed to query mock store with bare words");
+        assert_eq!(1, result.len());
+        assert!(matches!(search.query("  "), Err(Error::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_mock_explain() {
+        let _g = mock::test_scope();
+        let search = mock_search();
+
+        assert_eq!(
+            "mock: keeps the mock credentials whose User matches the regex \"foo\", ignoring case",
+            search.explain("user", "foo")
+        );
+        assert!(search
+            .explain("label", "foo")
+            .contains("cannot search by label"));
+    }
+
+    #[test]
+    fn test_mock_plan() {
+        let _g = mock::test_scope();
+        let search = mock_search().with_limit(Limit::Max(3));
+
+        // Planning doesn't search, so the pending error is left for the next search.
+        mock::set_next_error(Error::Connection("daemon stopped".to_string()));
+        let plan = search.plan("user", "foo");
+        assert!(matches!(search.by_user("foo"), Err(Error::Connection(_))));
+
+        assert_eq!("mock", plan.backend);
+        assert_eq!(Some("User"), plan.native_field);
+        assert_eq!("the mock store", plan.scope);
+        assert_eq!(None, plan.item_class);
+        assert_eq!("foo", plan.query);
+        assert_eq!(Limit::Max(3), plan.limit);
+        assert_eq!(
+            search.explain("user", "foo"),
+            format!("mock: {}", plan.explanation)
+        );
+    }
+
+    #[test]
+    fn test_mock_empty_as_ok() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let search = mock_search().empty_as_ok(true);
+
+        assert!(search
+            .by_user(&name)
+            .expect("Empty search should succeed")
+            .is_empty());
+        assert!(search
+            .query(&format!("user:{}", name))
+            .expect("Empty query should succeed")
+            .is_empty());
+        assert!(matches!(search.by_user("("), Err(Error::SearchError(_))));
+    }
+
+    #[test]
+    fn test_mock_regex_flags() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(
+            MockData::builder()
+                .service(&name)
+                .user(&name.to_lowercase())
+                .build(),
+        );
+        let search = mock_search();
+        let case_sensitive = search.clone().regex_flags(RegexFlags {
+            case_insensitive: false,
+            ..RegexFlags::default()
+        });
+
+        assert!(search.by_user(&name.to_uppercase()).is_ok());
+        assert!(matches!(
+            case_sensitive.by_user(&name.to_uppercase()),
+            Err(Error::NoResults)
+        ));
+        assert!(case_sensitive.by_user(&name.to_lowercase()).is_ok());
+    }
+
+    #[test]
+    fn test_mock_search_and_list() {
+        let _g = mock::test_scope();
+        let names = searchable_entries();
+        let search = mock_search();
+
+        assert_eq!(
+            List::list_credentials(&search.by_user(&names[0]), Limit::All),
+            search
+                .search_and_list("user", &names[0], Limit::All)
+                .expect("Failed to search and list")
+        );
+        assert!(matches!(
+            search.search_and_list("user", &generate_random_string(), Limit::All),
+            Err(Error::NoResults)
+        ));
+    }
+
+    #[test]
+    fn test_mock_by_smart() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(MockData::builder().service(&name).user(&name).build());
+        let search = mock_search();
+        // Dropping a run from the middle keeps the rest in order, but
+        // not contiguous, even ignoring case.
+        let scattered = format!("{}{}", &name[..10], &name[20..]);
+
+        let tier = |result: CredentialSearchResult| {
+            result
+                .expect("Smart search should match")
+                .values()
+                .next()
+                .and_then(|fields| fields.get(TIER_FIELD).cloned())
+        };
+        assert_eq!(
+            Some("exact".to_string()),
+            tier(search.by_smart("user", &name))
+        );
+        // The exact tier matches the whole value, and its case.
+        assert_eq!(
+            Some("substring".to_string()),
+            tier(search.by_smart("user", &name[..10]))
+        );
+        assert_eq!(
+            Some("substring".to_string()),
+            tier(search.by_smart("user", &name.to_uppercase()))
+        );
+        assert_eq!(
+            Some("fuzzy".to_string()),
+            tier(search.by_smart("user", &scattered))
+        );
+        assert!(matches!(
+            search
+                .clone()
+                .match_tiers(&[MatchTier::Exact])
+                .by_smart("user", &scattered),
+            Err(Error::NoResults)
+        ));
+    }
+
+    #[test]
+    fn test_mock_find_duplicates() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let other = generate_random_string();
+        let store = get_store();
+        store.add(MockData::builder().service(&name).user(&name).build());
+        store.add(MockData::builder().service(&name).user(&name).build());
+        store.add(MockData::builder().service(&name).user(&other).build());
+
+        let duplicates = mock_search()
+            .find_duplicates()
+            .expect("Failed to find duplicates");
+        let clusters: Vec<_> = duplicates
+            .iter()
+            .filter(|cluster| cluster[0].get("service") == Some(name.as_str()))
+            .collect();
+
+        assert_eq!(1, clusters.len());
+        assert_eq!(2, clusters[0].len());
+        assert!(clusters[0]
+            .iter()
+            .all(|result| result.get("user") == Some(name.as_str())));
+
+        // Listing every credential doesn't depend on the match mode.
+        let duplicates = mock_search()
+            .match_mode(MatchMode::Tokens)
+            .find_duplicates()
+            .expect("Failed to find duplicates in tokens mode");
+        assert!(duplicates
+            .iter()
+            .any(|cluster| cluster[0].get("service") == Some(name.as_str())));
+    }
+
+    #[test]
+    fn test_mock_distinct_services() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let store = get_store();
+        for service in ["b", "a", "b", "c"] {
+            store.add(
+                MockData::builder()
+                    .service(&format!("{}-{}", name, service))
+                    .user(&generate_random_string())
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let services: Vec<String> = search
+            .distinct_services(usize::MAX)
+            .expect("Failed to list services")
+            .into_iter()
+            .filter(|service| service.starts_with(&name))
+            .collect();
+        assert_eq!(
+            vec![
+                format!("{}-a", name),
+                format!("{}-b", name),
+                format!("{}-c", name)
+            ],
+            services
+        );
+        assert_eq!(
+            1,
+            search
+                .distinct_services(1)
+                .expect("Failed to list services")
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_mock_next_error() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(MockData::builder().user(&name).build());
+        let search = mock_search();
+
+        mock::set_next_error(Error::Connection("daemon stopped".to_string()));
+        assert!(matches!(
+            search.by_user(&name),
+            Err(Error::Connection(message)) if message == "daemon stopped"
+        ));
+        assert_eq!(
+            1,
+            search
+                .by_user(&name)
+                .expect("The error should apply once")
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_mock_field_cardinality() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let store = get_store();
+        for service in ["b", "a", "b", "b"] {
+            store.add(
+                MockData::builder()
+                    .service(&format!("{}-{}", name, service))
+                    .user(&generate_random_string())
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let counts = search
+            .field_cardinality("service")
+            .expect("Failed to count services");
+        assert_eq!(Some(&1), counts.get(&format!("{}-a", name)));
+        assert_eq!(Some(&3), counts.get(&format!("{}-b", name)));
+    }
+
+    #[test]
+    fn test_mock_by_into() {
+        let _g = mock::test_scope();
+        struct Account {
+            user: String,
+        }
+        impl FromSearchResult for Account {
+            fn from_search_result(result: &SearchResult) -> crate::Result<Account> {
+                Ok(Account {
+                    user: result.get("user").unwrap_or_default().to_string(),
+                })
+            }
+        }
+
+        let name = generate_random_string();
+        get_store().add(MockData::builder().service(&name).user(&name).build());
+        let search = mock_search();
+
+        let accounts: Vec<Account> = search
+            .by_into("service", &name)
+            .expect("Failed to search into accounts");
+        assert_eq!(1, accounts.len());
+        assert_eq!(name, accounts[0].user);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mock_by_deserialize() {
+        let _g = mock::test_scope();
+        #[derive(serde::Deserialize)]
+        struct Cred {
+            user: String,
+            service: String,
+        }
+        #[derive(serde::Deserialize)]
+        struct WithPort {
+            #[allow(dead_code)]
+            port: String,
+        }
+
+        let name = generate_random_string();
+        get_store().add(MockData::builder().service(&name).user("alice").build());
+        let search = mock_search();
+
+        let creds: Vec<Cred> = search
+            .by_deserialize("service", &name)
+            .expect("Failed to deserialize results");
+        assert_eq!(1, creds.len());
+        assert_eq!(
+            ("alice", name.as_str()),
+            (creds[0].user.as_str(), creds[0].service.as_str())
+        );
+        assert!(matches!(
+            search.by_deserialize::<WithPort>("service", &name),
+            Err(Error::Unexpected(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_by_raw() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(
+            MockData::builder()
+                .service(&name)
+                .user(&name)
+                .extra("pdmn", "ak")
+                .build(),
+        );
+        let search = mock_search().select(&["user"]);
+
+        let raw = search.by_raw("service", &name).expect("Failed to search");
+        assert!(raw["1"].contains_key("pdmn"));
+        assert!(raw["1"].contains_key("Service"));
+        assert!(!search.by_service(&name).expect("Failed to search")["1"].contains_key("pdmn"));
+    }
+
+    #[test]
+    fn test_mock_by_tokens() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(
+            MockData::builder()
+                .service(&format!("prod-{}-AWS", name))
+                .user(&name)
+                .build(),
+        );
+        let search = mock_search();
+
+        let result = search
+            .by_tokens("service", &format!("aws {} prod", name))
+            .expect("Failed to search by tokens");
+        assert_eq!(1, result.len());
+        assert!(matches!(
+            search.by_tokens("service", &format!("aws {} staging", name)),
+            Err(Error::NoResults)
+        ));
+        assert!(matches!(
+            search.by_tokens("service", "  "),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_service_prefix_suffix() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(
+            MockData::builder()
+                .service(&format!("com.{}.app", name))
+                .user(&name)
+                .build(),
+        );
+        let search = mock_search();
+
+        let prefix = search
+            .service_prefix(&format!("com.{}", name))
+            .expect("Failed to search by prefix");
+        assert_eq!(1, prefix.len());
+        let suffix = search
+            .service_suffix(&format!("{}.app", name))
+            .expect("Failed to search by suffix");
+        assert_eq!(1, suffix.len());
+        // Anchored, so neither matches the middle of the service.
+        assert!(matches!(
+            search.service_prefix(&name),
+            Err(Error::NoResults)
+        ));
+        assert!(matches!(
+            search.service_suffix(&format!("{}.ap", name)),
+            Err(Error::NoResults)
+        ));
+    }
+
+    #[test]
+    fn test_mock_by_any_of() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let other = generate_random_string();
+        get_store().add(MockData::builder().service(&name).user(&name).build());
+        get_store().add(MockData::builder().service(&other).user(&name).build());
+        let search = mock_search();
+
+        let result = search
+            .by_any_of(&[("service", &other), ("user", &name)])
+            .expect("Failed to search by any of");
+        assert_eq!(2, result.len());
+        assert_eq!(Some(&other), result["1"].get("Service"));
+        assert!(matches!(
+            search.by_any_of(&[("user", &other), ("service", &generate_random_string())]),
+            Err(Error::NoResults)
+        ));
+        assert!(matches!(
+            search.by_any_of(&[("user", &name), ("owner", &name)]),
+            Err(Error::Unexpected(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_limit_applies_after_filtering() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let store = get_store();
+        for _ in 0..5 {
+            store.add(
+                MockData::builder()
+                    .service(&generate_random_string())
+                    .user(&name)
+                    .build(),
+            );
+        }
+        store.add(MockData::builder().service(&name).user(&name).build());
+        let search = mock_search().with_limit(Limit::Max(1));
+
+        let result = search
+            .by_matcher("service", &ExactMatcher::new(&name))
+            .expect("The limit should apply to the matches");
+        assert_eq!(1, result.len());
+        let result = search
+            .query(&format!("user:{} service:{}", name, name))
+            .expect("The limit should apply to the matches");
+        assert_eq!(1, result.len());
+        assert_eq!(
+            1,
+            search
+                .by_matcher("user", &ExactMatcher::new(&name))
+                .expect("Failed to search by user")
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_mock_by_matcher() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(
+            MockData::builder()
+                .service(&format!("{}-v1.2", name))
+                .user(&name)
+                .build(),
+        );
+        get_store().add(
+            MockData::builder()
+                .service(&format!("{}-v1.10", name))
+                .user(&name)
+                .build(),
+        );
+        let search = mock_search();
+
+        let glob = GlobMatcher::new(&format!("{}-v1.?", name)).expect("Failed to build glob");
+        let result = search
+            .by_matcher("service", &glob)
+            .expect("Failed to search by glob");
+        assert_eq!(1, result.len());
+
+        let prefix = format!("{}-v1.", name);
+        let newer = |value: &str| {
+            value
+                .strip_prefix(&prefix)
+                .and_then(|minor| minor.parse::<u32>().ok())
+                .map_or(false, |minor| minor >= 10)
+        };
+        let result = search
+            .by_matcher("service", &newer)
+            .expect("Failed to search by closure");
+        assert_eq!(1, result.len());
+        assert_eq!(
+            Some(&format!("{}-v1.10", name)),
+            result
+                .values()
+                .next()
+                .and_then(|fields| fields.get("Service"))
+        );
+    }
+
+    #[test]
+    fn test_mock_cursor() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        for index in 0..5 {
+            get_store().add(
+                MockData::builder()
+                    .service(&format!("{}-{}", name, index))
+                    .user(&name)
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let mut cursor = search.cursor("user", &name);
+        let mut ids = Vec::new();
+        let mut services = HashSet::new();
+        for expected in [2, 2, 1, 0] {
+            let batch = cursor.next_batch(2).expect("Failed to get batch");
+            assert_eq!(expected, batch.len());
+            for result in batch {
+                ids.push(result.id().to_string());
+                services.insert(result.get("service").map(str::to_string));
+            }
+        }
+        assert_eq!(vec!["1", "2", "3", "4", "5"], ids);
+        assert_eq!(5, services.len());
+        assert_eq!(5, cursor.position());
+    }
+
+    #[test]
+    fn test_mock_native_query() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        for service in ["first", "second"] {
+            get_store().add(
+                MockData::builder()
+                    .service(&format!("{}-{}", name, service))
+                    .user(&name)
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let params = HashMap::from([
+            ("User".to_string(), name.clone()),
+            ("Service".to_string(), format!("{}-second", name)),
+        ]);
+        let result = search.native_query(params).expect("Failed native query");
+        assert_eq!(1, result.len());
+        assert_eq!(
+            Some(&format!("{}-second", name)),
+            result.get("1").and_then(|fields| fields.get("service"))
+        );
+
+        let params = HashMap::from([("User".to_string(), format!("{}-missing", name))]);
+        assert!(matches!(search.native_query(params), Err(Error::NoResults)));
+
+        let params = HashMap::from([("acct".to_string(), name)]);
+        match search.native_query(params) {
+            Err(Error::UnsupportedField(field)) => assert_eq!("acct", field),
+            result => panic!("Expected an unsupported field, got {:?}", result),
+        }
+        assert!(matches!(
+            search.native_query(HashMap::new()),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_scope() {
+        // A store of its own, as emptying the global one would race the other tests.
+        let store: &'static MockCredentialStore<MockData> = Box::leak(Box::default());
+        store.add(MockData::builder().user("before").build());
+
+        {
+            let _guard = store.scope();
+            assert!(store.get().is_empty());
+            store.add(MockData::builder().user("during").build());
+            assert_eq!(1, store.get().len());
+        }
+        let users: Vec<String> = store.get().iter().map(|data| data.user.clone()).collect();
+        assert_eq!(vec!["before".to_string()], users);
+
+        let result = std::panic::catch_unwind(|| {
+            let _guard = store.scope();
+            store.add(MockData::builder().user("panicked").build());
+            panic!("test panicked in its scope");
+        });
+        assert!(result.is_err());
+        assert_eq!(1, store.get().len());
+        drop(store.scope());
+    }
+
+    #[test]
+    fn test_mock_tag_matched_query() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let other = generate_random_string();
+        get_store().add(MockData::builder().service(&name).user(&name).build());
+        get_store().add(MockData::builder().service(&other).user(&name).build());
+        let search = mock_search().tag_matched_query(true);
+
+        let missing = generate_random_string();
+        let result = search
+            .by_any_of(&[("service", &other), ("service", &missing), ("user", &name)])
+            .expect("Failed to search by any of");
+        let matched = |query: &str| {
+            result
+                .values()
+                .filter(|fields| fields.get(MATCHED_QUERY_FIELD).map(String::as_str) == Some(query))
+                .count()
+        };
+        assert_eq!(1, matched(&other));
+        assert_eq!(0, matched(&missing));
+        assert_eq!(2, matched(&name));
+
+        let result = search
+            .query(&format!("user:{} service:{}", name, other))
+            .expect("Failed to query mock store");
+        let tags: Vec<&String> = result
+            .values()
+            .filter_map(|fields| fields.get(MATCHED_QUERY_FIELD))
+            .collect();
+        assert_eq!(vec![&format!("user:{} service:{}", name, other)], tags);
+    }
+
+    #[test]
+    fn test_mock_by_sorted_by() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        for (service, user) in [("b", "1"), ("a", "2"), ("b", "0")] {
+            get_store().add(
+                MockData::builder()
+                    .service(service)
+                    .target(&name)
+                    .user(user)
+                    .build(),
+            );
+        }
+        let search = mock_search();
+
+        let results = search
+            .by_sorted_by("target", &name, |a, b| {
+                a.get("service")
+                    .cmp(&b.get("service"))
+                    .then_with(|| a.get("user").cmp(&b.get("user")))
+            })
+            .expect("Failed to search sorted");
+        let order: Vec<(&str, &str)> = results
+            .iter()
+            .map(|result| {
+                (
+                    result.get("service").unwrap_or_default(),
+                    result.get("user").unwrap_or_default(),
+                )
+            })
+            .collect();
+        assert_eq!(vec![("a", "2"), ("b", "0"), ("b", "1")], order);
+    }
+
+    #[test]
+    fn test_mock_with_limit() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        for _ in 0..3 {
+            get_store().add(MockData::builder().service(&name).user(&name).build());
+        }
+        let search = mock_search();
+
+        let result = search
+            .clone()
+            .with_limit(Limit::Max(2))
+            .by_service(&name)
+            .expect("Failed to search with limit");
+        assert_eq!(2, result.len());
+        assert!(result.contains_key("1") && result.contains_key("2"));
+        assert!(matches!(
+            search.with_limit(Limit::Max(0)).by_service(&name),
+            Err(Error::NoResults)
+        ));
+    }
+
+    #[test]
+    fn test_mock_incremental() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let search = mock_search();
+        get_store().add(MockData::builder().service("first").user(&name).build());
+
+        let mut incremental = search
+            .incremental("user")
+            .expect("Failed to create incremental search");
+        get_store().add(MockData::builder().service("second").user(&name).build());
+
+        assert_eq!(1, incremental.query(&name[..20].to_uppercase()).len());
+        incremental.refresh().expect("Failed to refresh");
+        assert_eq!(2, incremental.query(&name).len());
+        assert!(incremental.query(&generate_random_string()).is_empty());
+    }
+
+    #[test]
+    fn test_mock_missing_field() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(MockData::builder().service(&name).user("").build());
+        get_store().add(MockData::builder().service(&name).user(&name).build());
+        let search = mock_search();
+
+        let result = search
+            .missing_field("user")
+            .expect("Failed to find credentials without a user");
+        let services: Vec<&str> = result
+            .values()
+            .filter_map(|fields| fields.get("Service").map(String::as_str))
+            .collect();
+
+        assert_eq!(
+            1,
+            services.iter().filter(|service| **service == name).count()
+        );
+        assert!(result.values().all(|fields| fields["User"].is_empty()));
+    }
+
+    #[test]
+    fn test_mock_hide_fields() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        get_store().add(
+            MockData::builder()
+                .service(&name)
+                .user(&name)
+                .extra("pdmn", "ak")
+                .extra("comment", "shown")
+                .build(),
+        );
+        let search = mock_search();
+
+        let hidden = search.by_service(&name).expect("Failed to search");
+        assert!(!hidden["1"].contains_key("pdmn"));
+        assert!(hidden["1"].contains_key("comment"));
+
+        let shown = search
+            .clone()
+            .hide_fields(&[])
+            .by_service(&name)
+            .expect("Failed to search");
+        assert!(shown["1"].contains_key("pdmn"));
+
+        let custom = search
+            .clone()
+            .hide_fields(&["Comment"])
+            .by_service(&name)
+            .expect("Failed to search");
+        assert!(custom["1"].contains_key("pdmn"));
+        assert!(!custom["1"].contains_key("comment"));
+
+        let selected = search
+            .select(&["pdmn"])
+            .by_service(&name)
+            .expect("Failed to search");
+        assert_eq!(Some("ak"), selected["1"].get("pdmn").map(String::as_str));
+    }
+
+    #[test]
+    fn no_results() {
+        let _g = mock::test_scope();
+        let name = generate_random_string();
+        let result = mock_search().by_service(&name).unwrap_err();
 
         assert!(matches!(result, Error::NoResults));
     }
 
     #[test]
     fn test_max_result() {
+        let _g = mock::test_scope();
         let name = generate_random_string();
         let store = get_store();
         let credential1 = MockData {
             service: "test-service1".to_string(),
             target: "test-target1".to_string(),
             user: name.clone(),
+            extra: HashMap::new(),
         };
 
         let credential2 = MockData {
             service: "test-service2".to_string(),
             target: "test-target2".to_string(),
             user: name.clone(),
+            extra: HashMap::new(),
         };
 
         let credential3 = MockData {
             service: "test-service3".to_string(),
             target: "test-target3".to_string(),
             user: name.clone(),
+            extra: HashMap::new(),
         };
         store.add(credential1);
         store.add(credential2);
         store.add(credential3);
-        let result = set_default_credential_search(mock::default_credential_search())
-            .expect("Failed to create mock search")
-            .by_user(&name);
+        let result = mock_search().by_user(&name);
 
         let list = List::list_credentials(&result, Limit::Max(2));
 
         let result_set = list.lines().count();
 
-        assert_eq!(8, result_set);
+        assert_eq!(14, result_set);
     }
 }
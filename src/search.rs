@@ -1,9 +1,110 @@
-use super::Result;
-use std::collections::HashMap;
+use super::{Error, Result};
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime};
+
+// Bounds on the compiled size of a query's regex, so a
+// pathological pattern fails fast instead of hanging the search.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+const REGEX_DFA_SIZE_LIMIT: usize = 1 << 20;
 
 /// The API that [credential search](CredentialSearch) implements.
 pub trait CredentialSearchApi {
     fn by(&self, by: &str, query: &str) -> Result<HashMap<String, HashMap<String, String>>>;
+    /// Search with the [options](SearchOptions) configured on a [Search](crate::Search).
+    ///
+    /// Backends that don't honor any options can rely on the default,
+    /// which ignores them, calls [by](CredentialSearchApi::by) and reports no warnings.
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        _options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        self.by(by, query)
+            .map(|results| CredentialSearchResultWithWarnings {
+                results,
+                ..Default::default()
+            })
+    }
+    /// The name of the backend, used when reporting on a search.
+    fn backend_name(&self) -> &'static str {
+        "custom"
+    }
+    /// Describes the search the backend would run, without running it.
+    ///
+    /// Backends should name the native field the generic one maps to
+    /// and how values are matched. The default can only repeat the input.
+    fn explain(&self, by: &str, query: &str, _options: &SearchOptions) -> String {
+        format!("searches {} for \"{}\"", by, query)
+    }
+    /// Lists every credential the backend can see, for audits that have
+    /// no query. The default can't enumerate the store and fails.
+    fn all(&self, _options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        Err(Error::SearchError(format!(
+            "{} can't list every credential",
+            self.backend_name()
+        )))
+    }
+    /// Searches one page of the results, skipping the first `offset`
+    /// matches and keeping at most `count`, for a [SearchCursor](crate::SearchCursor).
+    ///
+    /// The default runs the search up to the end of the page and drops
+    /// the matches before it. Backends that can skip matches while they
+    /// enumerate should override it, so earlier pages aren't held.
+    fn by_page(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+        offset: usize,
+        count: usize,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        let end = offset.saturating_add(count);
+        let listed = self.by_with_options(
+            by,
+            query,
+            &SearchOptions {
+                limit: Limit::Max(end),
+                ..options.clone()
+            },
+        )?;
+        let results = sorted_by_id(listed.results)
+            .into_iter()
+            .skip(offset)
+            .enumerate()
+            .map(|(index, (_, inner_map))| ((index + 1).to_string(), inner_map))
+            .collect();
+
+        Ok(CredentialSearchResultWithWarnings {
+            results: non_empty(results)?,
+            ..listed
+        })
+    }
+    /// Searches with parameters in the backend's own terms, for attributes
+    /// the generic fields don't cover. The default can't map any parameter
+    /// and fails with an [UnsupportedField](Error::UnsupportedField).
+    fn native_query(
+        &self,
+        params: &HashMap<String, String>,
+        _options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        let mut keys: Vec<&str> = params.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        Err(Error::UnsupportedField(keys.join(", ")))
+    }
+    /// Plans the search the backend would run, without running it or
+    /// touching the store. The default knows nothing of where the
+    /// backend looks, so it plans a search of the whole store.
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        SearchPlan::new(self, by, query, options, "the whole store")
+    }
+    /// Checks whether the backend's store can be reached, without searching it.
+    /// The default has nothing to check and reports it available.
+    fn health_check(&self) -> Result<BackendHealth> {
+        Ok(BackendHealth::new(self.backend_name(), None))
+    }
 }
 
 /// A thread-safe implementation of the [CredentialSearch API](CredentialSearchApi).
@@ -17,6 +118,26 @@ pub type CredentialSearch = dyn CredentialSearchApi + Send + Sync;
 /// to select a credential and get its metadata housed in the inner map.
 pub type CredentialSearchResult = Result<HashMap<String, HashMap<String, String>>>;
 
+/// Search results grouped by service, then by user, from [tree](crate::Search::tree).
+pub type SearchTree = BTreeMap<String, BTreeMap<String, Vec<SearchResult<'static>>>>;
+
+/// Search results along with any non-fatal warnings the backend ran into.
+///
+/// A warning describes something that was left out of the results,
+/// such as a locked collection that was skipped, rather than failing
+/// the whole search. `truncated` is set when the search stopped at its
+/// [deadline](crate::Search::deadline), so the results are incomplete.
+/// `ambiguous` is set when linux-keyutils found several keys whose
+/// description matches the query, where the kernel's single key lookup
+/// would have returned an arbitrary one of them.
+#[derive(Debug, Default)]
+pub struct CredentialSearchResultWithWarnings {
+    pub results: HashMap<String, HashMap<String, String>>,
+    pub warnings: Vec<String>,
+    pub truncated: bool,
+    pub ambiguous: bool,
+}
+
 /// The API that [credential list](CredentialList) implements.
 pub trait CredentialListApi {
     fn list_credentials(
@@ -29,7 +150,1855 @@ pub trait CredentialListApi {
 pub type CredentialList = dyn CredentialListApi + Send + Sync;
 
 /// Type matching enum, allows for constraint of the amount of results returned to the user.
+///
+/// `Bytes` bounds the approximate size of the listed results, counting
+/// the length of every ID, key and value, rather than their number.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Limit {
+    #[default]
     All,
-    Max(i64),
+    Max(usize),
+    Bytes(usize),
+}
+
+impl Limit {
+    // The number of items a Max limit keeps. Other limits don't bound
+    // the number of items.
+    pub(crate) fn max_items(self) -> usize {
+        match self {
+            Limit::Max(max) => max,
+            Limit::All | Limit::Bytes(_) => usize::MAX,
+        }
+    }
+}
+
+/// Options set on a [Search](crate::Search) and passed through to the backend.
+///
+/// Each option documents the platforms that honor it, the rest ignore it.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub id_format: IdFormat,
+    /// Skip locked collections instead of failing the search (secret-service).
+    pub skip_locked: bool,
+    /// Only keep these metadata fields in each result (all platforms).
+    pub select: Option<Vec<String>>,
+    /// Drop these metadata fields from each result, [DEFAULT_HIDDEN_FIELDS]
+    /// when `None` (all platforms).
+    pub hide_fields: Option<Vec<String>>,
+    /// How values are matched against the query (all but macOS).
+    pub match_mode: MatchMode,
+    /// Caps the items fetched by the query itself (macOS).
+    pub limit: Limit,
+    /// Skip credentials with invalid UTF-16 instead of replacing it (Windows).
+    pub strict_utf16: bool,
+    /// Read and decode each credential's blob, to search by and return it (Windows).
+    pub include_blob: bool,
+    /// Give up on enumerating the credentials after this long (Windows).
+    pub enumeration_timeout: Option<Duration>,
+    /// Stop searching further keyrings or collections once this passes
+    /// (keyutils, secret-service). Not saved with a `SearchConfig`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub deadline: Option<Instant>,
+    /// Only search credentials of these `CRED_TYPE`s, all when `None` (Windows).
+    pub cred_types: Option<Vec<u32>>,
+    /// Search each collection on its own thread (secret-service).
+    pub parallel_collections: bool,
+    /// Convert every attribute in full, rather than as `simplify_dict` does (macOS).
+    pub full_attributes: bool,
+    /// Only keep items created after this time (secret-service).
+    pub created_after: Option<SystemTime>,
+    /// Only keep items last modified before this time (secret-service).
+    pub modified_before: Option<SystemTime>,
+    /// Only search credentials the current user owns and can use
+    /// (keyutils, Windows, secret-service).
+    pub owned_only: bool,
+    /// Store each result's relevance to the query under `score` (all platforms).
+    pub ranked: bool,
+    /// Store the query each result matched under [MATCHED_QUERY_FIELD] (all platforms).
+    pub tag_matched_query: bool,
+    /// How regex queries are compiled (Windows, mock, and the `Regex` match mode).
+    pub regex_flags: RegexFlags,
+    /// Return an empty result rather than `NoResults` when nothing matches (all platforms).
+    pub empty_as_ok: bool,
+    /// The class of keychain item searched (macOS).
+    pub item_class: ItemClassKind,
+    /// Only search this keychain, rather than the search list (macOS).
+    pub keychain: Option<Keychain>,
+    /// Only keep internet passwords for this protocol, such as `https` (macOS).
+    pub protocol: Option<String>,
+    /// Only keep internet passwords with this authentication type, such as `html_form` (macOS).
+    pub auth_type: Option<String>,
+    /// The tiers [by_smart](crate::Search::by_smart) escalates through,
+    /// [DEFAULT_MATCH_TIERS] when `None` (all platforms).
+    pub match_tiers: Option<Vec<MatchTier>>,
+    /// Return each result's fields as the store names them, skipping
+    /// any renaming, hiding, selection or ranking (all platforms).
+    pub raw: bool,
+}
+
+/// A search's backend and options, which can be saved to a file
+/// and turned back into a [Search](crate::Search) with
+/// [from_config](crate::Search::from_config).
+///
+/// The options are flattened alongside the backend, so a profile
+/// reads `{ "backend": "mock", "match_mode": "IgnoreCase", "limit": { "Max": 5 } }`.
+/// Any field left out takes its default.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// The backend searched, `mock` or the platform default's name,
+    /// the platform default when `None`.
+    pub backend: Option<String>,
+    #[serde(flatten)]
+    pub options: SearchOptions,
+}
+
+/// How a value is matched against the query, see [match_mode](crate::Search::match_mode)
+/// for what each backend honors.
+///
+/// On secret-service and iOS, `Exact` uses the native search, and the
+/// other modes list every item and filter client-side, which is slower
+/// on large stores. Windows and the mock backend match `Exact`,
+/// `IgnoreCase` and `Contains` as a regex.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// The value equals the query.
+    #[default]
+    Exact,
+    /// The value equals the query, ignoring case.
+    IgnoreCase,
+    /// The value contains the query, ignoring case.
+    Contains,
+    /// The value matches the query as a case-insensitive regex.
+    Regex,
+    /// The value contains every whitespace-separated word of the query,
+    /// in any order, ignoring case, so `aws prod` finds `prod-aws-account`.
+    /// Windows and the mock backend honor this too, see [by_tokens](crate::Search::by_tokens).
+    Tokens,
+    /// The value starts with the query. Windows and the mock backend
+    /// honor this too, see [service_prefix](crate::Search::service_prefix).
+    Prefix,
+    /// The value ends with the query. Windows and the mock backend
+    /// honor this too, see [service_suffix](crate::Search::service_suffix).
+    Suffix,
+}
+
+/// How closely a [by_smart](crate::Search::by_smart) search matched,
+/// stored under [TIER_FIELD] in each result.
+///
+/// The substring and fuzzy tiers search with the [Regex](MatchMode::Regex)
+/// match mode, so they only widen the search on the regex-based Windows
+/// and mock backends, and on secret-service, keyutils and iOS. macOS
+/// only ever matches at the exact tier.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchTier {
+    /// The search as configured, with the query as given. On the
+    /// regex-based Windows and mock backends, the value is the query,
+    /// matching case.
+    Exact,
+    /// The value contains the query, ignoring case.
+    Substring,
+    /// The value contains the query's characters in order, ignoring
+    /// case, so `gthb` finds `github`.
+    Fuzzy,
+}
+
+/// The tiers [by_smart](crate::Search::by_smart) escalates through
+/// unless [match_tiers](crate::Search::match_tiers) is set.
+pub const DEFAULT_MATCH_TIERS: &[MatchTier] =
+    &[MatchTier::Exact, MatchTier::Substring, MatchTier::Fuzzy];
+
+/// The key the matching tier is stored under by [by_smart](crate::Search::by_smart).
+pub const TIER_FIELD: &str = "tier";
+
+/// The key the query a result matched is stored under, when
+/// [tagged](crate::Search::tag_matched_query).
+pub const MATCHED_QUERY_FIELD: &str = "matched_query";
+
+impl MatchTier {
+    /// The tier's name, as stored under [TIER_FIELD].
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchTier::Exact => "exact",
+            MatchTier::Substring => "substring",
+            MatchTier::Fuzzy => "fuzzy",
+        }
+    }
+    // The match mode and query the tier searches with, None keeps the search's own.
+    pub(crate) fn query(&self, query: &str) -> Option<(MatchMode, String)> {
+        match self {
+            MatchTier::Exact => None,
+            MatchTier::Substring => Some((MatchMode::Regex, regex::escape(query))),
+            MatchTier::Fuzzy => {
+                let pattern = query
+                    .chars()
+                    .map(|c| regex::escape(&c.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(".*");
+                Some((MatchMode::Regex, pattern))
+            }
+        }
+    }
+}
+
+/// A field to search by with [by_field](crate::Search::by_field), so a
+/// misspelled field fails to compile rather than to search.
+///
+/// `Account` is the user's account name, searched as [User](SearchField::User)
+/// on every backend. `Label` is secret-service's item label and macOS'
+/// `labl`, which macOS' target already searches, the other platforms
+/// have no label. Parses from its name, ignoring case.
+///
+/// # Example
+///     use keyring_search::SearchField;
+///     assert_eq!(SearchField::User, "User".parse().unwrap());
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchField {
+    Target,
+    User,
+    Service,
+    Label,
+    Account,
+}
+
+impl SearchField {
+    /// The field's name, as a search takes it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchField::Target => "target",
+            SearchField::User => "user",
+            SearchField::Service => "service",
+            SearchField::Label => "label",
+            SearchField::Account => "account",
+        }
+    }
+    // The name the backend's search takes for the field, None when the
+    // backend has no such field. Backends this doesn't know, such as
+    // custom ones, are passed the field's own name.
+    pub(crate) fn by_name(&self, backend: &str) -> Option<&'static str> {
+        match (self, backend) {
+            (SearchField::User | SearchField::Account, _) => Some("user"),
+            (SearchField::Label, "secret-service") => Some("label"),
+            // keyutils ignores the field, matching every one against the description.
+            (SearchField::Label, "macos" | "keyutils") => Some("target"),
+            (SearchField::Label, "windows" | "ios" | "mock") => None,
+            (field, _) => Some(field.name()),
+        }
+    }
+}
+
+impl std::fmt::Display for SearchField {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for SearchField {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<SearchField> {
+        [
+            SearchField::Target,
+            SearchField::User,
+            SearchField::Service,
+            SearchField::Label,
+            SearchField::Account,
+        ]
+        .into_iter()
+        .find(|field| field.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            Error::InvalidQuery(format!(
+                "Unknown search field {}, expected target, user, service, label or account",
+                name
+            ))
+        })
+    }
+}
+
+/// Flags for compiling the regex a query is matched with.
+///
+/// Windows and the mock backend match every query as a regex, as do
+/// secret-service and iOS in the [Regex](MatchMode::Regex) match mode.
+/// The default is case-insensitive and Unicode-aware, as searches
+/// have always been.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexFlags {
+    /// Letters match both upper and lower case.
+    pub case_insensitive: bool,
+    /// `^` and `$` match at the start and end of each line, such as in a multi-line comment.
+    pub multi_line: bool,
+    /// `.` also matches `\n`.
+    pub dot_matches_new_line: bool,
+    /// Classes such as `\w` match Unicode rather than only ASCII.
+    pub unicode: bool,
+}
+
+impl Default for RegexFlags {
+    fn default() -> RegexFlags {
+        RegexFlags {
+            case_insensitive: true,
+            multi_line: false,
+            dot_matches_new_line: false,
+            unicode: true,
+        }
+    }
+}
+
+impl RegexFlags {
+    // Describes the flags that change how a regex matches, for explaining a search.
+    pub(crate) fn describe(&self) -> String {
+        let mut flags = Vec::new();
+        if self.case_insensitive {
+            flags.push("ignoring case");
+        }
+        if self.multi_line {
+            flags.push("multi-line");
+        }
+        if self.dot_matches_new_line {
+            flags.push("dot matching newlines");
+        }
+        if !self.unicode {
+            flags.push("ASCII only");
+        }
+        flags.iter().map(|flag| format!(", {}", flag)).collect()
+    }
+}
+
+/// The class of keychain item a macOS search looks for.
+///
+/// Certificates, keys and identities have no service or account,
+/// so they can only be searched by target, their label. Their
+/// attributes, such as a certificate's subject and issuer, are
+/// returned as the keychain stores them, binary ones lossily
+/// decoded as UTF-8.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ItemClassKind {
+    #[default]
+    GenericPassword,
+    InternetPassword,
+    Certificate,
+    Key,
+    Identity,
+}
+
+/// A macOS keychain to scope a search to.
+///
+/// Without one, macOS searches every keychain in the user's search
+/// list. Admins looking for machine-wide items, such as Wi-Fi
+/// passwords, search the System keychain.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keychain {
+    /// The user's login keychain.
+    Login,
+    /// The System keychain, shared by every user of the machine.
+    System,
+    /// The keychain of the common domain.
+    Common,
+    /// A keychain file, such as `~/Library/Keychains/work.keychain-db`.
+    Path(String),
+}
+
+/// Controls how the outer map's ID is built on backends that
+/// do not number their results.
+///
+/// Only iOS honors this, every other platform returns numeric IDs.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdFormat {
+    /// IDs range from 1 to the size of the outer map, as on Windows.
+    Numeric,
+    /// `Label: {user}@{service}`.
+    #[default]
+    UserAtService,
+    /// `{user}@{service}`, the label without its `Label: ` prefix.
+    LabelOnly,
+}
+
+/// How long a single search took, reported to the observer
+/// set with [with_observer](crate::Search::with_observer).
+#[derive(Debug, Clone)]
+pub struct SearchTiming {
+    pub backend: &'static str,
+    pub field: String,
+    pub query_len: usize,
+    pub duration: Duration,
+    pub result_count: usize,
+}
+
+/// The state of a backend's store, from [health_check](crate::Search::health_check).
+///
+/// Each backend fills in what it can check and leaves the rest empty:
+/// secret-service counts its collections and names the locked ones,
+/// Windows counts the credentials it enumerated and macOS names the
+/// default keychain `default` when it is locked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackendHealth {
+    pub backend: &'static str,
+    pub available: bool,
+    pub error: Option<String>,
+    pub collections: Option<usize>,
+    pub locked: Vec<String>,
+    pub credentials: Option<usize>,
+}
+
+impl BackendHealth {
+    // A report of the backend, available unless there's an error.
+    pub(crate) fn new(backend: &'static str, error: Option<String>) -> BackendHealth {
+        BackendHealth {
+            backend,
+            available: error.is_none(),
+            error,
+            ..Default::default()
+        }
+    }
+}
+
+/// The search a backend would run, from [plan](crate::Search::plan),
+/// worked out without connecting to the store.
+///
+/// The structured counterpart of [explain](crate::Search::explain), for
+/// logging or a dry run. `scope` names where the backend would look,
+/// such as the session keyring or every collection, and `item_class`
+/// is only set on macOS.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchPlan {
+    pub backend: &'static str,
+    pub field: String,
+    /// The backend's own name for the field, see [canonical_field],
+    /// `None` when it can't search by it.
+    pub native_field: Option<&'static str>,
+    pub scope: String,
+    pub item_class: Option<ItemClassKind>,
+    pub query: String,
+    pub match_mode: MatchMode,
+    pub limit: Limit,
+    /// The backend's description of the search, as by [explain](crate::Search::explain).
+    pub explanation: String,
+}
+
+impl SearchPlan {
+    pub(crate) fn new(
+        api: &(impl CredentialSearchApi + ?Sized),
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+        scope: &str,
+    ) -> SearchPlan {
+        SearchPlan {
+            backend: api.backend_name(),
+            field: by.to_string(),
+            native_field: canonical_field(api.backend_name(), by),
+            scope: scope.to_string(),
+            item_class: None,
+            query: query.to_string(),
+            match_mode: options.match_mode,
+            limit: options.limit,
+            explanation: api.explain(by, query, options),
+        }
+    }
+}
+
+/// A single credential of a search result, with accessors that bridge
+/// the backends' differing field names.
+///
+/// [get](SearchResult::get) matches keys case-insensitively, then by
+/// their known aliases, so `get("user")` finds Windows' `User`,
+/// secret-service's `username` and macOS' `acct` alike. It borrows
+/// from a search result, or owns its data once [into_owned](SearchResult::into_owned).
+#[derive(Debug, Clone)]
+pub struct SearchResult<'a> {
+    id: Cow<'a, str>,
+    fields: Cow<'a, HashMap<String, String>>,
+}
+
+// The native field each backend searches for a generic one.
+const CANONICAL_FIELDS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "windows",
+        &[
+            ("target", "Target"),
+            ("raw_target", "Raw Target"),
+            ("service", "Target"),
+            ("user", "User"),
+        ],
+    ),
+    (
+        "secret-service",
+        &[
+            ("user", "username"),
+            ("target", "application"),
+            ("service", "service"),
+            ("schema", "xdg:schema"),
+            ("label", "label"),
+            ("path", "path"),
+        ],
+    ),
+    (
+        "macos",
+        &[("target", "labl"), ("service", "svce"), ("user", "acct")],
+    ),
+    ("ios", &[("service", "svce"), ("user", "acct")]),
+    (
+        "keyutils",
+        &[
+            ("target", "description"),
+            ("service", "description"),
+            ("user", "description"),
+        ],
+    ),
+    (
+        "mock",
+        &[
+            ("target", "Target"),
+            ("service", "Service"),
+            ("user", "User"),
+        ],
+    ),
+];
+
+/// The fields every result of a [Search](crate::Search) carries, alongside
+/// the backend's own, an empty string when the backend doesn't know them.
+pub const BASELINE_FIELDS: &[&str] = &["service", "user", "target"];
+
+// Adds the baseline fields a result lacks, copied from the native field
+// the backend searches for each. keyutils matches every field against
+// the description, so only keyring-rs descriptions, `keyring-rs:user@service`,
+// give a user and service. The Windows no user sentinel is left empty.
+pub(crate) fn normalize_fields(
+    results: &mut HashMap<String, HashMap<String, String>>,
+    backend: &str,
+) {
+    for inner_map in results.values_mut() {
+        for field in BASELINE_FIELDS {
+            if inner_map.contains_key(*field) {
+                continue;
+            }
+            let value = match (backend, canonical_field(backend, field)) {
+                ("keyutils", _) => inner_map
+                    .get("description")
+                    .and_then(|description| description.strip_prefix("keyring-rs:"))
+                    .and_then(|description| description.rsplit_once('@'))
+                    .and_then(|(user, service)| match *field {
+                        "user" => Some(user),
+                        "service" => Some(service),
+                        _ => None,
+                    })
+                    .map(str::to_string),
+                (_, Some(native)) => inner_map.get(native).cloned(),
+                (_, None) => None,
+            };
+            let value = value.filter(|value| value != NO_USER).unwrap_or_default();
+            inner_map.insert(field.to_string(), value);
+        }
+    }
+}
+
+/// Returns the native field a backend searches for a generic one.
+///
+/// The backend is named as by [backend_name](crate::Search::backend_name),
+/// and the field as passed to a search, ignoring case, so `user` is
+/// `acct` on macOS and `username` on secret-service. keyutils matches
+/// every field against the key's description. `None` when the backend
+/// or field isn't known, or the backend can't search by the field.
+///
+/// # Example
+///     use keyring_search::search::canonical_field;
+///     assert_eq!(Some("application"), canonical_field("secret-service", "target"));
+pub fn canonical_field(backend: &str, field: &str) -> Option<&'static str> {
+    let (_, fields) = CANONICAL_FIELDS.iter().find(|(name, _)| *name == backend)?;
+
+    fields
+        .iter()
+        .find(|(generic, _)| generic.eq_ignore_ascii_case(field))
+        .map(|(_, native)| *native)
+}
+
+// Field names that mean the same thing across backends.
+const FIELD_ALIASES: &[&[&str]] = &[
+    &["user", "username", "account", "acct"],
+    &["service", "svce"],
+    &["label", "labl"],
+    &["target", "application"],
+];
+
+impl<'a> SearchResult<'a> {
+    pub fn new(id: &'a str, fields: &'a HashMap<String, String>) -> SearchResult<'a> {
+        SearchResult {
+            id: Cow::Borrowed(id),
+            fields: Cow::Borrowed(fields),
+        }
+    }
+    /// Copies any borrowed data, so the result outlives the search result.
+    pub fn into_owned(self) -> SearchResult<'static> {
+        SearchResult {
+            id: Cow::Owned(self.id.into_owned()),
+            fields: Cow::Owned(self.fields.into_owned()),
+        }
+    }
+    /// The credential's ID, its key in the outer map.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// Returns the value of a field, matching its key case-insensitively,
+    /// or failing that, any of the key's aliases.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        if let Some(value) = self.get_exact(key) {
+            return Some(value);
+        }
+
+        FIELD_ALIASES
+            .iter()
+            .find(|aliases| aliases.iter().any(|alias| alias.eq_ignore_ascii_case(key)))
+            .and_then(|aliases| aliases.iter().find_map(|alias| self.get_exact(alias)))
+    }
+    /// Builds a [keyring::Entry] from the credential's service and user fields.
+    ///
+    /// Windows credentials are named by their target, which keyring-rs
+    /// writes as `{user}.{service}`, so on a Windows result the service
+    /// is taken from there. Returns [Invalid](keyring::Error::Invalid)
+    /// if either field is missing, or if a Windows target isn't one
+    /// keyring-rs wrote for the user.
+    #[cfg(feature = "keyring")]
+    pub fn to_entry(&self) -> keyring::Result<keyring::Entry> {
+        let (service, user) = self.entry_fields()?;
+
+        keyring::Entry::new(service, user)
+    }
+    // The service and user keyring-rs identifies the credential by.
+    #[cfg(feature = "keyring")]
+    pub(crate) fn entry_fields(&self) -> keyring::Result<(&str, &str)> {
+        let field = |name: &str| {
+            self.get(name).ok_or_else(|| {
+                keyring::Error::Invalid(name.to_string(), "missing from search result".to_string())
+            })
+        };
+        let user = field("user")?;
+
+        // Only Windows results have a raw target.
+        if self.get_exact("Raw Target").is_none() {
+            return Ok((field("service")?, user));
+        }
+        let service = field("target")?
+            .strip_prefix(user)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .filter(|service| !service.is_empty())
+            .ok_or_else(|| {
+                keyring::Error::Invalid(
+                    "service".to_string(),
+                    "not in a keyring-rs `{user}.{service}` target".to_string(),
+                )
+            })?;
+
+        Ok((service, user))
+    }
+    /// A hash of the credential's [baseline](BASELINE_FIELDS) `service`,
+    /// `user` and `target` fields, which identify it across searches,
+    /// unlike its [id](SearchResult::id), which is its position in one.
+    ///
+    /// The fields are copied from each backend's own: Windows' `Target`,
+    /// which also stands for the service, and `User`, secret-service's
+    /// `service`, `username` and `application`, macOS' `svce`, `acct`
+    /// and `labl`, and iOS' `svce` and `acct`. keyutils takes the service and user from a keyring-rs
+    /// description, and has no target. A missing field hashes as empty.
+    /// The hash is FNV-1a, so it is the same in every process and build
+    /// and can be stored, but two credentials differing in other fields
+    /// alone share one.
+    pub fn identity_hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let mut hash = FNV_OFFSET_BASIS;
+        for field in BASELINE_FIELDS {
+            let value = self.get(field).unwrap_or_default();
+            // 0xff never occurs in UTF-8, so it separates the fields unambiguously.
+            for byte in value.bytes().chain(std::iter::once(0xff)) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+        hash
+    }
+    /// The keys of the credential's fields, as the backend named them.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().map(String::as_str)
+    }
+    /// The number of fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+    // An exact key wins over one differing in case, such as the
+    // baseline `user` field alongside a backend's own `User`.
+    fn get_exact(&self, key: &str) -> Option<&str> {
+        if let Some(value) = self.fields.get(key) {
+            return Some(value);
+        }
+
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Builds a value from a credential, for [by_into](crate::Search::by_into).
+///
+/// Implement it to extract your own struct from the fields. With the
+/// `serde` feature, types that implement `Deserialize` can be read with
+/// `Search::by_deserialize` instead.
+///
+/// # Example
+///     use keyring_search::{FromSearchResult, Result, SearchResult};
+///     struct Account(String);
+///     impl FromSearchResult for Account {
+///         fn from_search_result(result: &SearchResult) -> Result<Account> {
+///             Ok(Account(result.get("user").unwrap_or_default().to_string()))
+///         }
+///     }
+pub trait FromSearchResult: Sized {
+    fn from_search_result(result: &SearchResult) -> Result<Self>;
+}
+
+// Deserializes the credential's fields as a map of strings. A field
+// that's missing or doesn't parse fails with Unexpected.
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_result<T: serde::de::DeserializeOwned>(
+    result: &SearchResult,
+) -> Result<T> {
+    let fields = result
+        .fields
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()));
+    let deserializer = serde::de::value::MapDeserializer::<_, serde::de::value::Error>::new(fields);
+
+    T::deserialize(deserializer)
+        .map_err(|err| Error::Unexpected(format!("credential {}: {}", result.id(), err)))
+}
+
+/// Wraps each credential of a search result in a [SearchResult], ordered by ID.
+///
+/// # Example
+///     let search = keyring_search::Search::default();
+///     if let Ok(result) = search.by_user("Mr. Foo Bar") {
+///         for credential in keyring_search::search::results(&result) {
+///             println!("{}: {:?}", credential.id(), credential.get("service"));
+///         }
+///     }
+pub fn results(search_result: &HashMap<String, HashMap<String, String>>) -> Vec<SearchResult<'_>> {
+    let mut results: Vec<SearchResult> = search_result
+        .iter()
+        .map(|(id, fields)| SearchResult::new(id, fields))
+        .collect();
+    results.sort_by(|a, b| {
+        (a.id().parse::<i32>().unwrap_or(0), a.id())
+            .cmp(&(b.id().parse::<i32>().unwrap_or(0), b.id()))
+    });
+    results
+}
+
+/// Combinators over a [CredentialSearchResult], applied to the `Ok` map.
+///
+/// Errors pass through untouched. Credentials keep their IDs, and a
+/// combinator that leaves no credentials returns [NoResults](Error::NoResults).
+///
+/// # Example
+///     use keyring_search::{CredentialSearchResultExt, Search};
+///     let results = Search::default()
+///         .by_service("aws")
+///         .filter_results(|result| result.get("Persist") == Some("Enterprise"))
+///         .limit(5);
+pub trait CredentialSearchResultExt {
+    /// Keeps the credentials the predicate returns true for.
+    fn filter_results(self, predicate: impl FnMut(SearchResult) -> bool) -> CredentialSearchResult;
+    /// Keeps the first `max` credentials, ordered by ID.
+    fn limit(self, max: usize) -> CredentialSearchResult;
+    /// Keeps one credential per identity, the one with the lowest ID.
+    ///
+    /// A credential's identity is its service, or its target where it has
+    /// none, along with its user, as [find_duplicates](crate::Search::find_duplicates)
+    /// groups them. When `case_insensitive`, `GitHub` and `github` are the
+    /// same service. Credentials missing either field are all kept.
+    fn dedup_by_identity(self, case_insensitive: bool) -> CredentialSearchResult;
+    /// Converts the map into [SearchResults], ordered by ID.
+    fn into_results(self) -> Result<SearchResults>;
+}
+
+impl CredentialSearchResultExt for CredentialSearchResult {
+    fn filter_results(
+        self,
+        mut predicate: impl FnMut(SearchResult) -> bool,
+    ) -> CredentialSearchResult {
+        let mut result = self?;
+        result.retain(|id, fields| predicate(SearchResult::new(id, fields)));
+        non_empty(result)
+    }
+
+    fn limit(self, max: usize) -> CredentialSearchResult {
+        let result = self?;
+        non_empty(sorted_by_id(result).into_iter().take(max).collect())
+    }
+
+    fn dedup_by_identity(self, case_insensitive: bool) -> CredentialSearchResult {
+        let result = self?;
+        let mut seen = HashSet::new();
+        let mut deduped = HashMap::new();
+        for (id, fields) in sorted_by_id(result) {
+            let identity = identity(&SearchResult::new(&id, &fields)).map(|(service, user)| {
+                if case_insensitive {
+                    (service.to_lowercase(), user.to_lowercase())
+                } else {
+                    (service, user)
+                }
+            });
+            if identity.map_or(true, |identity| seen.insert(identity)) {
+                deduped.insert(id, fields);
+            }
+        }
+        non_empty(deduped)
+    }
+
+    fn into_results(self) -> Result<SearchResults> {
+        self.map(SearchResults::from)
+    }
+}
+
+/// Search results as a list of [SearchEntry], ordered by ID, rather
+/// than the map of a [CredentialSearchResult].
+///
+/// Built from the map with [into_results](CredentialSearchResultExt::into_results),
+/// or `From`, so typed entries can be iterated without going through
+/// the listed text.
+///
+/// # Example
+///     use keyring_search::{CredentialSearchResultExt, Search};
+///     if let Ok(results) = Search::default().by_user("Mr. Foo Bar").into_results() {
+///         for entry in &results {
+///             println!("{}: {:?}", entry.id, entry.attributes.get("Service"));
+///         }
+///     }
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchResults(Vec<SearchEntry>);
+
+/// A single credential of [SearchResults].
+///
+/// The ID is the credential's key in the map. Keys that aren't numbers,
+/// such as iOS' `Label: {user}@{service}`, are numbered by their
+/// position in ID order instead, starting at 1.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchEntry {
+    pub id: u32,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl SearchResults {
+    pub fn entries(&self) -> &[SearchEntry] {
+        &self.0
+    }
+    pub fn into_entries(self) -> Vec<SearchEntry> {
+        self.0
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, SearchEntry> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<HashMap<String, HashMap<String, String>>> for SearchResults {
+    fn from(result: HashMap<String, HashMap<String, String>>) -> SearchResults {
+        SearchResults(
+            sorted_by_id(result)
+                .into_iter()
+                .enumerate()
+                .map(|(index, (id, fields))| SearchEntry {
+                    id: id.parse().unwrap_or(index as u32 + 1),
+                    attributes: fields.into_iter().collect(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl IntoIterator for SearchResults {
+    type Item = SearchEntry;
+    type IntoIter = std::vec::IntoIter<SearchEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SearchResults {
+    type Item = &'a SearchEntry;
+    type IntoIter = std::slice::Iter<'a, SearchEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+// A credential's service, which Windows names its target, and user,
+// if it has both. Empty baseline fields count as missing.
+pub(crate) fn identity(result: &SearchResult) -> Option<(String, String)> {
+    let known = |field: &str| result.get(field).filter(|value| !value.is_empty());
+    let service = known("service").or_else(|| known("target"))?;
+    let user = known("user")?;
+    Some((service.to_string(), user.to_string()))
+}
+
+fn non_empty(result: HashMap<String, HashMap<String, String>>) -> CredentialSearchResult {
+    if result.is_empty() {
+        Err(Error::NoResults)
+    } else {
+        Ok(result)
+    }
+}
+
+/// Merges several search results into one.
+///
+/// The credentials of each `Ok` result are combined, dropping exact
+/// duplicates, and renumbered from 1 in the order they were passed.
+/// A [NoResults](Error::NoResults) result counts as empty, any other
+/// error is returned as is. If every result is empty, so is the merge.
+///
+/// # Example
+///     let search = keyring_search::Search::default();
+///     let results = keyring_search::search::merge([
+///         search.by_user("Mr. Foo Bar"),
+///         search.by_service("Bar inc."),
+///     ]);
+pub fn merge(results: impl IntoIterator<Item = CredentialSearchResult>) -> CredentialSearchResult {
+    let mut merged: Vec<HashMap<String, String>> = Vec::new();
+    for result in results {
+        let result = match result {
+            Ok(result) => result,
+            Err(Error::NoResults) => continue,
+            Err(err) => return Err(err),
+        };
+
+        for (_, inner_map) in sorted_by_id(result) {
+            if !merged.contains(&inner_map) {
+                merged.push(inner_map);
+            }
+        }
+    }
+
+    if merged.is_empty() {
+        return Err(Error::NoResults);
+    }
+
+    Ok(merged
+        .into_iter()
+        .enumerate()
+        .map(|(index, inner_map)| ((index + 1).to_string(), inner_map))
+        .collect())
+}
+
+// Runs a native query as field filters over every credential, for
+// backends with no query language of their own. Each key must be one
+// of the backend's result fields, and the field must equal its value.
+pub(crate) fn filter_native(
+    api: &(impl CredentialSearchApi + ?Sized),
+    params: &HashMap<String, String>,
+    fields: &[&str],
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort_unstable();
+    if let Some(key) = keys.into_iter().find(|key| !fields.contains(&key.as_str())) {
+        return Err(Error::UnsupportedField(key.to_string()));
+    }
+
+    // The limit applies to the filtered credentials, not those listed.
+    let listed = api.all(&SearchOptions {
+        limit: Limit::All,
+        ..options.clone()
+    })?;
+    let results: HashMap<String, HashMap<String, String>> = sorted_by_id(listed.results)
+        .into_iter()
+        .map(|(_, inner_map)| inner_map)
+        .filter(|inner_map| {
+            params
+                .iter()
+                .all(|(key, value)| inner_map.get(key) == Some(value))
+        })
+        .take(options.limit.max_items())
+        .enumerate()
+        .map(|(index, inner_map)| ((index + 1).to_string(), inner_map))
+        .collect();
+
+    Ok(CredentialSearchResultWithWarnings {
+        results: non_empty(results)?,
+        warnings: listed.warnings,
+        truncated: listed.truncated,
+        ..Default::default()
+    })
+}
+
+// Standard base64, with padding, for the binary values macOS
+// and Windows return.
+#[cfg(any(
+    all(target_os = "macos", feature = "platform-macos"),
+    all(target_os = "windows", feature = "platform-windows"),
+    test
+))]
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |triple, (i, byte)| {
+            triple | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Compiles the regex used by every regex-based search, per the flags.
+//
+// Patterns exceeding the size limits return an InvalidQuery error.
+pub(crate) fn build_regex(query: &str, flags: &RegexFlags) -> Result<Regex> {
+    RegexBuilder::new(query)
+        .case_insensitive(flags.case_insensitive)
+        .multi_line(flags.multi_line)
+        .dot_matches_new_line(flags.dot_matches_new_line)
+        .unicode(flags.unicode)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .build()
+        .map_err(|err| match err {
+            regex::Error::CompiledTooBig(_) => Error::InvalidQuery(format!("Regex Error, {}", err)),
+            _ => Error::SearchError(format!("Regex Error, {}", err)),
+        })
+}
+
+/// Decides whether a field's value matches, for [by_matcher](crate::Search::by_matcher).
+///
+/// Implement it for matching the built-in matchers don't cover, such as
+/// comparing versions or IP ranges. A closure taking the value is a
+/// matcher too.
+///
+/// # Example
+///     use keyring_search::search::Matcher;
+///     let numeric = |value: &str| value.parse::<u32>().is_ok();
+///     assert!(numeric.matches("42"));
+pub trait Matcher {
+    fn matches(&self, value: &str) -> bool;
+}
+
+impl<F: Fn(&str) -> bool> Matcher for F {
+    fn matches(&self, value: &str) -> bool {
+        self(value)
+    }
+}
+
+/// Matches a value equal to the query.
+#[derive(Debug, Clone)]
+pub struct ExactMatcher(String);
+
+impl ExactMatcher {
+    pub fn new(query: &str) -> ExactMatcher {
+        ExactMatcher(query.to_string())
+    }
+}
+
+impl Matcher for ExactMatcher {
+    fn matches(&self, value: &str) -> bool {
+        value == self.0
+    }
+}
+
+/// Matches a value containing the query, ignoring case.
+#[derive(Debug, Clone)]
+pub struct SubstringMatcher(String);
+
+impl SubstringMatcher {
+    pub fn new(query: &str) -> SubstringMatcher {
+        SubstringMatcher(query.to_lowercase())
+    }
+}
+
+impl Matcher for SubstringMatcher {
+    fn matches(&self, value: &str) -> bool {
+        value.to_lowercase().contains(&self.0)
+    }
+}
+
+/// Matches a value the regex matches, compiled per the [RegexFlags].
+#[derive(Debug, Clone)]
+pub struct RegexMatcher(Regex);
+
+impl RegexMatcher {
+    /// Compiles the pattern, failing as a [Regex](MatchMode::Regex) search would.
+    pub fn new(pattern: &str, flags: &RegexFlags) -> Result<RegexMatcher> {
+        Ok(RegexMatcher(build_regex(pattern, flags)?))
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn matches(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+/// Matches a whole value against a glob, ignoring case, where `*`
+/// matches any run of characters and `?` any one character.
+#[derive(Debug, Clone)]
+pub struct GlobMatcher(Regex);
+
+impl GlobMatcher {
+    pub fn new(glob: &str) -> Result<GlobMatcher> {
+        let mut pattern = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                _ => pattern.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        pattern.push('$');
+
+        let flags = RegexFlags {
+            dot_matches_new_line: true,
+            ..RegexFlags::default()
+        };
+        Ok(GlobMatcher(build_regex(&pattern, &flags)?))
+    }
+}
+
+impl Matcher for GlobMatcher {
+    fn matches(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+/// Matches a value holding the query's characters in order, with
+/// anything between them, ignoring case, as the [Fuzzy](MatchTier::Fuzzy)
+/// tier does.
+#[derive(Debug, Clone)]
+pub struct FuzzyMatcher(String);
+
+impl FuzzyMatcher {
+    pub fn new(query: &str) -> FuzzyMatcher {
+        FuzzyMatcher(query.to_lowercase())
+    }
+}
+
+impl Matcher for FuzzyMatcher {
+    fn matches(&self, value: &str) -> bool {
+        let value = value.to_lowercase();
+        let mut chars = value.chars();
+        self.0.chars().all(|c| chars.any(|v| v == c))
+    }
+}
+
+// A query compared to values client-side, per the MatchMode.
+pub(crate) enum ValueMatch {
+    Exact(String),
+    IgnoreCase(String),
+    Contains(String),
+    Regex(Regex),
+    Tokens(Vec<String>),
+    Prefix(String),
+    Suffix(String),
+}
+
+impl ValueMatch {
+    pub(crate) fn new(query: &str, options: &SearchOptions) -> Result<ValueMatch> {
+        Ok(match options.match_mode {
+            MatchMode::Exact => ValueMatch::Exact(query.to_string()),
+            MatchMode::IgnoreCase => ValueMatch::IgnoreCase(query.to_lowercase()),
+            MatchMode::Contains => ValueMatch::Contains(query.to_lowercase()),
+            MatchMode::Regex => ValueMatch::Regex(build_regex(query, &options.regex_flags)?),
+            MatchMode::Tokens => {
+                let tokens: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+                if tokens.is_empty() {
+                    return Err(Error::InvalidQuery(
+                        "query has no words to match".to_string(),
+                    ));
+                }
+                ValueMatch::Tokens(tokens)
+            }
+            MatchMode::Prefix => ValueMatch::Prefix(query.to_string()),
+            MatchMode::Suffix => ValueMatch::Suffix(query.to_string()),
+        })
+    }
+
+    // Windows and the mock backend match every query as a regex,
+    // apart from in the Tokens, Prefix and Suffix match modes.
+    pub(crate) fn regex_or_tokens(query: &str, options: &SearchOptions) -> Result<ValueMatch> {
+        match options.match_mode {
+            MatchMode::Tokens | MatchMode::Prefix | MatchMode::Suffix => {
+                ValueMatch::new(query, options)
+            }
+            _ => Ok(ValueMatch::Regex(build_regex(query, &options.regex_flags)?)),
+        }
+    }
+
+    // Describes the values regex_or_tokens matches, for explaining a search.
+    pub(crate) fn describe_regex_or_tokens(query: &str, options: &SearchOptions) -> String {
+        let regex_options;
+        let options = match options.match_mode {
+            MatchMode::Tokens | MatchMode::Prefix | MatchMode::Suffix => options,
+            _ => {
+                regex_options = SearchOptions {
+                    match_mode: MatchMode::Regex,
+                    ..options.clone()
+                };
+                &regex_options
+            }
+        };
+        ValueMatch::describe(query, options)
+    }
+
+    // Describes the values matched, for explaining a search.
+    pub(crate) fn describe(query: &str, options: &SearchOptions) -> String {
+        match options.match_mode {
+            MatchMode::Exact => format!("is \"{}\"", query),
+            MatchMode::IgnoreCase => format!("is \"{}\", ignoring case", query),
+            MatchMode::Contains => format!("contains \"{}\", ignoring case", query),
+            MatchMode::Regex => format!(
+                "matches the regex \"{}\"{}",
+                query,
+                options.regex_flags.describe()
+            ),
+            MatchMode::Tokens => format!(
+                "contains every word of \"{}\", in any order, ignoring case",
+                query
+            ),
+            MatchMode::Prefix => format!("starts with \"{}\"", query),
+            MatchMode::Suffix => format!("ends with \"{}\"", query),
+        }
+    }
+
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        match self {
+            ValueMatch::Exact(query) => value == query,
+            ValueMatch::IgnoreCase(query) => value.to_lowercase() == *query,
+            ValueMatch::Contains(query) => value.to_lowercase().contains(query.as_str()),
+            ValueMatch::Regex(regex) => regex.is_match(value),
+            ValueMatch::Tokens(tokens) => {
+                let value = value.to_lowercase();
+                tokens.iter().all(|token| value.contains(token.as_str()))
+            }
+            ValueMatch::Prefix(query) => value.starts_with(query.as_str()),
+            ValueMatch::Suffix(query) => value.ends_with(query.as_str()),
+        }
+    }
+}
+
+/// The user Windows reports for credentials that have none.
+pub const NO_USER: &str = "NO USER";
+
+/// The key the relevance score is stored under when ranking results.
+pub const SCORE_FIELD: &str = "score";
+
+/// Scores how well a value matches the query, lower is better.
+///
+/// Ignoring case, an exact match is 0, a prefix 1 and a substring 2.
+/// Anything else is 3 plus the edit distance between the two, so a
+/// fuzzy match always ranks below a substring match.
+pub fn relevance(query: &str, value: &str) -> usize {
+    let query = query.to_lowercase();
+    let value = value.to_lowercase();
+
+    if value == query {
+        0
+    } else if value.starts_with(&query) {
+        1
+    } else if value.contains(&query) {
+        2
+    } else {
+        3 + edit_distance(&query, &value)
+    }
+}
+
+// The Levenshtein distance between two strings, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Stores each result's relevance to the query under SCORE_FIELD, scoring
+// the searched field where the result has it, or else its best field.
+pub(crate) fn score_results(
+    results: &mut HashMap<String, HashMap<String, String>>,
+    by: &str,
+    query: &str,
+) {
+    for (id, inner_map) in results.iter_mut() {
+        let search_result = SearchResult::new(id, inner_map);
+        let score = match search_result.get(by) {
+            Some(value) => relevance(query, value),
+            None => inner_map
+                .values()
+                .map(|value| relevance(query, value))
+                .min()
+                .unwrap_or(usize::MAX),
+        };
+        inner_map.insert(SCORE_FIELD.to_string(), score.to_string());
+    }
+}
+
+// The fields a `field:value` term of a query can name.
+const QUERY_FIELDS: &[&str] = &["user", "service", "target", "label", "schema"];
+
+// A single term of a query, `field:value`, or a bare value matching any field.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct QueryTerm {
+    pub field: Option<String>,
+    pub value: String,
+}
+
+impl QueryTerm {
+    // Whether the result's field, or any field when bare, contains the value, ignoring case.
+    pub fn is_match(&self, result: &SearchResult) -> bool {
+        let value = self.value.to_lowercase();
+        match &self.field {
+            Some(field) => result.get(field).map_or(false, |field_value| {
+                field_value.to_lowercase().contains(&value)
+            }),
+            None => result
+                .fields
+                .values()
+                .any(|field_value| field_value.to_lowercase().contains(&value)),
+        }
+    }
+}
+
+// Splits a query such as `user:alice service:"my app" work` into its terms.
+// Double quotes group a value with spaces or colons, and field names are
+// matched case-insensitively against QUERY_FIELDS.
+pub(crate) fn parse_query(query: &str) -> Result<Vec<QueryTerm>> {
+    let mut terms = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut field = None;
+        let mut value = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        while let Some(c) = chars.next_if(|c| in_quotes || !c.is_whitespace()) {
+            match c {
+                '"' => {
+                    quoted = true;
+                    in_quotes = !in_quotes;
+                }
+                ':' if !quoted && field.is_none() && !value.is_empty() => {
+                    field = Some(std::mem::take(&mut value).to_lowercase());
+                }
+                _ => value.push(c),
+            }
+        }
+
+        if in_quotes {
+            return Err(Error::InvalidQuery("unterminated quote".to_string()));
+        }
+        if let Some(field) = &field {
+            if !QUERY_FIELDS.contains(&field.as_str()) {
+                return Err(Error::InvalidQuery(format!(
+                    "unknown field `{}`, expected one of: {}",
+                    field,
+                    QUERY_FIELDS.join(", ")
+                )));
+            }
+            if value.is_empty() {
+                return Err(Error::InvalidQuery(format!("no value for `{}`", field)));
+            }
+        }
+        terms.push(QueryTerm { field, value });
+    }
+
+    if terms.is_empty() {
+        return Err(Error::InvalidQuery("empty query".to_string()));
+    }
+
+    Ok(terms)
+}
+
+/// The internal fields left out of results unless asked for.
+///
+/// These are the macOS and iOS keychain attributes that say little
+/// about the credential but can leak internals: the access group,
+/// which names the app's team ID, the protection domain, the item's
+/// hash, its user data and its tombstone flag.
+pub const DEFAULT_HIDDEN_FIELDS: &[&str] = &["agrp", "musr", "pdmn", "sha1", "tomb"];
+
+// Drops the given fields, compared case-insensitively, from each result.
+pub(crate) fn hide_fields<S: AsRef<str>>(
+    results: &mut HashMap<String, HashMap<String, String>>,
+    fields: &[S],
+) {
+    for inner_map in results.values_mut() {
+        inner_map.retain(|key, _| {
+            !fields
+                .iter()
+                .any(|field| field.as_ref().eq_ignore_ascii_case(key))
+        });
+    }
+}
+
+// Keeps only the given fields, compared case-insensitively, and the score
+// of a ranked search, in each result.
+pub(crate) fn select_fields(
+    results: &mut HashMap<String, HashMap<String, String>>,
+    fields: &[String],
+) {
+    for inner_map in results.values_mut() {
+        inner_map.retain(|key, _| {
+            key == SCORE_FIELD || fields.iter().any(|field| field.eq_ignore_ascii_case(key))
+        });
+    }
+}
+
+// Orders results by their numeric ID, falling back to the ID itself
+// for backends whose IDs aren't numbers.
+pub(crate) fn sorted_by_id(
+    result: HashMap<String, HashMap<String, String>>,
+) -> Vec<(String, HashMap<String, String>)> {
+    let mut entries: Vec<(String, HashMap<String, String>)> = result.into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| {
+        (a.parse::<i32>().unwrap_or(0), a).cmp(&(b.parse::<i32>().unwrap_or(0), b))
+    });
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        build_regex, canonical_field, merge, normalize_fields, parse_query, relevance, results,
+        CredentialSearchResult, CredentialSearchResultExt, ExactMatcher, FuzzyMatcher, GlobMatcher,
+        Matcher, QueryTerm, RegexFlags, RegexMatcher, SearchField, SearchResult, SearchResults,
+        SubstringMatcher, NO_USER,
+    };
+    use crate::Error;
+
+    fn result(users: &[&str]) -> CredentialSearchResult {
+        let mut outer_map = HashMap::new();
+        for (index, user) in users.iter().enumerate() {
+            let mut inner_map = HashMap::new();
+            inner_map.insert("user".to_string(), user.to_string());
+            outer_map.insert((index + 1).to_string(), inner_map);
+        }
+        Ok(outer_map)
+    }
+
+    #[test]
+    fn test_merge() {
+        let merged = merge([
+            result(&["foo", "bar"]),
+            Err(Error::NoResults),
+            result(&["bar", "baz"]),
+        ])
+        .expect("Failed to merge results");
+
+        let users: Vec<&str> = (1..=3)
+            .map(|id| merged[&id.to_string()]["user"].as_str())
+            .collect();
+        assert_eq!(vec!["foo", "bar", "baz"], users);
+    }
+
+    #[test]
+    fn test_dedup_by_identity() {
+        let mut outer_map = HashMap::new();
+        for (id, service, user) in [
+            ("1", "GitHub", "alice"),
+            ("2", "github", "alice"),
+            ("3", "GitHub", "alice"),
+            ("4", "github", "bob"),
+        ] {
+            let mut inner_map = HashMap::new();
+            inner_map.insert("service".to_string(), service.to_string());
+            inner_map.insert("user".to_string(), user.to_string());
+            outer_map.insert(id.to_string(), inner_map);
+        }
+        let ids = |result: CredentialSearchResult| {
+            let mut ids: Vec<String> = result
+                .expect("Dedup should keep credentials")
+                .into_keys()
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(
+            vec!["1", "2", "4"],
+            ids(Ok(outer_map.clone()).dedup_by_identity(false))
+        );
+        assert_eq!(vec!["1", "4"], ids(Ok(outer_map).dedup_by_identity(true)));
+    }
+
+    #[test]
+    fn test_search_field_names() {
+        for field in [
+            SearchField::Target,
+            SearchField::User,
+            SearchField::Service,
+            SearchField::Label,
+            SearchField::Account,
+        ] {
+            let parsed: SearchField = field
+                .to_string()
+                .to_uppercase()
+                .parse()
+                .expect("Failed to parse field");
+            assert_eq!(field, parsed);
+        }
+        assert!(matches!(
+            "usr".parse::<SearchField>(),
+            Err(Error::InvalidQuery(_))
+        ));
+        assert_eq!(Some("target"), SearchField::Label.by_name("macos"));
+        assert_eq!(Some("label"), SearchField::Label.by_name("secret-service"));
+        assert_eq!(None, SearchField::Label.by_name("windows"));
+        assert_eq!(Some("user"), SearchField::Account.by_name("ios"));
+    }
+
+    #[test]
+    fn test_search_results_non_numeric_ids() {
+        let outer_map: HashMap<String, HashMap<String, String>> =
+            ["Label: bob@mail", "Label: alice@mail"]
+                .iter()
+                .map(|id| (id.to_string(), HashMap::new()))
+                .collect();
+
+        let ids: Vec<u32> = SearchResults::from(outer_map)
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(vec![1, 2], ids);
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        assert!(matches!(
+            merge([Err(Error::NoResults), Err(Error::NoResults)]),
+            Err(Error::NoResults)
+        ));
+        assert!(matches!(
+            merge([result(&["foo"]), Err(Error::Unexpected("test".to_string()))]),
+            Err(Error::Unexpected(_))
+        ));
+    }
+
+    fn term(field: Option<&str>, value: &str) -> QueryTerm {
+        QueryTerm {
+            field: field.map(str::to_string),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            vec![
+                term(Some("user"), "alice"),
+                term(Some("service"), "aws"),
+                term(None, "work")
+            ],
+            parse_query("  User:alice service:aws work ").expect("Failed to parse query")
+        );
+    }
+
+    #[test]
+    fn test_parse_query_quoted() {
+        assert_eq!(
+            vec![
+                term(Some("service"), "my app"),
+                term(None, "https://example.com"),
+                term(Some("user"), "a:b"),
+            ],
+            parse_query(r#"service:"my app" "https://example.com" user:a:b"#)
+                .expect("Failed to parse query")
+        );
+    }
+
+    #[test]
+    fn test_parse_query_invalid() {
+        for query in ["owner:alice", "user:", r#"user:"alice"#, "   "] {
+            assert!(
+                matches!(parse_query(query), Err(Error::InvalidQuery(_))),
+                "{} should not parse",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversized_regex() {
+        let err = build_regex(r"\w{1000}{1000}", &RegexFlags::default()).unwrap_err();
+
+        assert!(matches!(err, Error::InvalidQuery(_)));
+    }
+
+    #[test]
+    fn test_regex_flags() {
+        let comment = "first line\nSecond line";
+        let default = build_regex("^second", &RegexFlags::default()).expect("Valid regex");
+        let multi_line = RegexFlags {
+            multi_line: true,
+            ..RegexFlags::default()
+        };
+        let case_sensitive = RegexFlags {
+            multi_line: true,
+            case_insensitive: false,
+            ..RegexFlags::default()
+        };
+
+        assert!(!default.is_match(comment));
+        assert!(build_regex("^second", &multi_line)
+            .expect("Valid regex")
+            .is_match(comment));
+        assert!(!build_regex("^second", &case_sensitive)
+            .expect("Valid regex")
+            .is_match(comment));
+        assert!(build_regex(
+            "line.Second",
+            &RegexFlags {
+                dot_matches_new_line: true,
+                ..RegexFlags::default()
+            }
+        )
+        .expect("Valid regex")
+        .is_match(comment));
+    }
+
+    #[test]
+    fn test_search_result_get() {
+        let mut outer_map = HashMap::new();
+        let mut inner_map = HashMap::new();
+        inner_map.insert("acct".to_string(), "foo".to_string());
+        inner_map.insert("Service".to_string(), "bar".to_string());
+        outer_map.insert("2".to_string(), inner_map.clone());
+        outer_map.insert("1".to_string(), inner_map);
+
+        let results = results(&outer_map);
+
+        assert_eq!(
+            vec!["1", "2"],
+            results.iter().map(|r| r.id()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("foo"), results[0].get("user"));
+        assert_eq!(Some("bar"), results[0].get("service"));
+        assert_eq!(Some("bar"), results[0].get("svce"));
+        assert_eq!(None, results[0].get("label"));
+        assert_eq!(2, results[0].len());
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_to_entry() {
+        let mut inner_map = HashMap::new();
+        inner_map.insert("acct".to_string(), "foo".to_string());
+        let result = super::SearchResult::new("1", &inner_map);
+
+        assert!(matches!(
+            result.to_entry(),
+            Err(keyring::Error::Invalid(field, _)) if field == "service"
+        ));
+
+        inner_map.insert("svce".to_string(), "bar".to_string());
+        let result = super::SearchResult::new("1", &inner_map);
+        assert!(result.to_entry().is_ok());
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_to_entry_windows() {
+        let mut inner_map: HashMap<String, String> = [
+            ("Target", "alice.github.com"),
+            ("Raw Target", "LegacyGeneric:target=alice.github.com"),
+            ("User", "alice"),
+            (
+                "Comment",
+                "keyring-rs v2.3.3 for service 'github.com', user 'alice'",
+            ),
+            ("service", "alice.github.com"),
+            ("user", "alice"),
+            ("target", "alice.github.com"),
+        ]
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+        let result = super::SearchResult::new("1", &inner_map);
+        assert_eq!(
+            ("github.com", "alice"),
+            result.entry_fields().expect("Failed to read entry fields")
+        );
+
+        inner_map.insert("target".to_string(), "git:https://github.com".to_string());
+        let result = super::SearchResult::new("1", &inner_map);
+        assert!(matches!(
+            result.to_entry(),
+            Err(keyring::Error::Invalid(field, _)) if field == "service"
+        ));
+    }
+
+    #[test]
+    fn test_filter_and_limit() {
+        let filtered = result(&["foo", "bar", "baz", "qux"])
+            .filter_results(|result| result.get("user") != Some("bar"))
+            .limit(2)
+            .expect("Failed to filter results");
+
+        let mut ids: Vec<&str> = filtered.keys().map(String::as_str).collect();
+        ids.sort();
+        assert_eq!(vec!["1", "3"], ids);
+        assert!(matches!(
+            result(&["foo"]).filter_results(|_| false),
+            Err(Error::NoResults)
+        ));
+        assert!(matches!(
+            Err(Error::Unexpected("test".to_string())).limit(1),
+            Err(Error::Unexpected(_))
+        ));
+    }
+
+    #[test]
+    fn test_canonical_field() {
+        assert_eq!(Some("acct"), canonical_field("macos", "user"));
+        assert_eq!(Some("labl"), canonical_field("macos", "Target"));
+        assert_eq!(
+            Some("application"),
+            canonical_field("secret-service", "target")
+        );
+        assert_eq!(Some("Target"), canonical_field("windows", "service"));
+        assert_eq!(Some("description"), canonical_field("keyutils", "user"));
+        assert_eq!(None, canonical_field("ios", "target"));
+        assert_eq!(None, canonical_field("custom", "user"));
+    }
+
+    #[test]
+    fn test_matchers() {
+        let flags = RegexFlags::default();
+        assert!(ExactMatcher::new("alice").matches("alice"));
+        assert!(!ExactMatcher::new("alice").matches("Alice"));
+        assert!(SubstringMatcher::new("AWS").matches("prod-aws-1"));
+        assert!(RegexMatcher::new("^prod-\\d$", &flags)
+            .expect("Failed to build regex matcher")
+            .matches("PROD-1"));
+        let glob = GlobMatcher::new("prod-*.example.?om").expect("Failed to build glob matcher");
+        assert!(glob.matches("prod-db.example.com"));
+        assert!(!glob.matches("prod-db.exampleXcom"));
+        assert!(!glob.matches("my-prod-db.example.com"));
+        assert!(FuzzyMatcher::new("pdb").matches("Prod-DB"));
+        assert!(!FuzzyMatcher::new("bdp").matches("Prod-DB"));
+    }
+
+    #[test]
+    fn test_normalize_fields() {
+        let result = |fields: &[(&str, &str)]| {
+            HashMap::from([(
+                "1".to_string(),
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect::<HashMap<String, String>>(),
+            )])
+        };
+        let mut macos = result(&[("acct", "alice"), ("svce", "aws")]);
+        let mut windows = result(&[("User", NO_USER), ("Target", "git")]);
+        let mut keyutils = result(&[("description", "keyring-rs:bob@example.com@git")]);
+        normalize_fields(&mut macos, "macos");
+        normalize_fields(&mut windows, "windows");
+        normalize_fields(&mut keyutils, "keyutils");
+
+        assert_eq!("aws", macos["1"]["service"]);
+        assert_eq!("alice", macos["1"]["user"]);
+        assert_eq!("", macos["1"]["target"]);
+        assert_eq!("alice", macos["1"]["acct"]);
+        assert_eq!("", windows["1"]["user"]);
+        assert_eq!("git", windows["1"]["target"]);
+        assert_eq!("bob@example.com", keyutils["1"]["user"]);
+        assert_eq!("git", keyutils["1"]["service"]);
+        assert_eq!("", keyutils["1"]["target"]);
+    }
+
+    #[test]
+    fn test_relevance() {
+        assert_eq!(0, relevance("git", "Git"));
+        assert_eq!(1, relevance("git", "github"));
+        assert_eq!(2, relevance("git", "digital-ocean"));
+        assert_eq!(4, relevance("git", "gut"));
+    }
+
+    #[test]
+    fn test_identity_hash() {
+        let fields = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+        let first = fields(&[("service", "mail"), ("user", "alice")]);
+        // Another search, numbering it differently, with the native names and more fields.
+        let second = fields(&[("svce", "mail"), ("acct", "alice"), ("pdmn", "ak")]);
+        let other = fields(&[("service", "mai"), ("user", "lalice")]);
+
+        let hash = SearchResult::new("1", &first).identity_hash();
+        assert_eq!(0x9f57_8557_aed6_fe03, hash);
+        assert_eq!(hash, SearchResult::new("7", &second).identity_hash());
+        assert_ne!(hash, SearchResult::new("1", &other).identity_hash());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!("", super::base64(b""));
+        assert_eq!("Zg==", super::base64(b"f"));
+        assert_eq!("Zm8=", super::base64(b"fo"));
+        assert_eq!("Zm9v", super::base64(b"foo"));
+        assert_eq!("Zm9vYmFy", super::base64(b"foobar"));
+        assert_eq!("/+8=", super::base64(&[0xff, 0xef]));
+    }
 }
@@ -1,13 +1,31 @@
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use secret_service::blocking::SecretService;
+use secret_service::blocking::{Collection, SecretService};
 use secret_service::EncryptionType;
 
-use super::error::Error as ErrorCode;
-use super::search::{CredentialSearch, CredentialSearchApi, CredentialSearchResult};
+use super::error::{Error as ErrorCode, Result};
+use super::search::{
+    canonical_field, BackendHealth, CredentialSearch, CredentialSearchApi, CredentialSearchResult,
+    CredentialSearchResultWithWarnings, Limit, MatchMode, SearchOptions, SearchPlan, ValueMatch,
+};
 
 pub struct SsCredentialSearch {}
 
+// The attribute naming the schema an item was stored with.
+const SCHEMA_ATTRIBUTE: &str = "xdg:schema";
+
+// The longest query, in bytes, passed on to the secret-service.
+const MAX_QUERY_LEN: usize = 4096;
+
+// The D-Bus error returned while the secret-service daemon is being activated.
+const SERVICE_UNKNOWN: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
+
+// How long to wait for the daemon to come up before retrying.
+const SERVICE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
 /// Returns the Secret service default credential search structure.
 ///
 /// This creates a new search structure. The by method has no concrete search types
@@ -19,19 +37,252 @@ pub fn default_credential_search() -> Box<CredentialSearch> {
 
 impl CredentialSearchApi for SsCredentialSearch {
     fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
-        let by = match by.to_ascii_lowercase().as_str() {
-            "user" => "username",
-            "target" => "application",
-            "service" => "service",
-            _ => {
+        self.by_with_options(by, query, &SearchOptions::default())
+            .map(|result| result.results)
+    }
+
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        search_by(None, by, query, options)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "secret-service"
+    }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        explain(by, query, options)
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        SearchPlan::new(self, by, query, options, scope(options))
+    }
+
+    fn all(&self, options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        search_collections(None, ItemMatch::All, options)
+    }
+
+    fn native_query(
+        &self,
+        params: &HashMap<String, String>,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        native_query(None, params, options)
+    }
+
+    fn by_page(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+        offset: usize,
+        count: usize,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        let item_match = item_match(by, query, options)?;
+        search_page(None, item_match, options, offset, count)
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        Ok(health_check(None))
+    }
+}
+
+/// A Secret service credential search that uses an existing connection.
+///
+/// Apps that already hold a [SecretService] connection, such as one
+/// opened for the `keyring` crate, can search over it instead of
+/// opening another, sharing its encrypted session.
+pub struct SsConnectedCredentialSearch {
+    ss: SecretService<'static>,
+}
+
+/// Returns a Secret service credential search over the given connection.
+pub fn connected_credential_search(ss: SecretService<'static>) -> Box<CredentialSearch> {
+    Box::new(SsConnectedCredentialSearch { ss })
+}
+
+impl CredentialSearchApi for SsConnectedCredentialSearch {
+    fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
+        self.by_with_options(by, query, &SearchOptions::default())
+            .map(|result| result.results)
+    }
+
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        search_by(Some(&self.ss), by, query, options)
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "secret-service"
+    }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        explain(by, query, options)
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        SearchPlan::new(self, by, query, options, scope(options))
+    }
+
+    fn all(&self, options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        search_collections(Some(&self.ss), ItemMatch::All, options)
+    }
+
+    fn native_query(
+        &self,
+        params: &HashMap<String, String>,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        native_query(Some(&self.ss), params, options)
+    }
+
+    fn by_page(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+        offset: usize,
+        count: usize,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        let item_match = item_match(by, query, options)?;
+        search_page(Some(&self.ss), item_match, options, offset, count)
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        Ok(health_check(Some(&self.ss)))
+    }
+}
+
+// Names the collections a search would look in, skipping the locked
+// ones as search_collection does.
+fn scope(options: &SearchOptions) -> &'static str {
+    match options.skip_locked || options.owned_only {
+        true => "every unlocked collection",
+        false => "every collection",
+    }
+}
+
+// Describes the search search_by would run, as mapped there.
+fn explain(by: &str, query: &str, options: &SearchOptions) -> String {
+    let items = match by.to_ascii_lowercase().as_str() {
+        "label" => format!("whose label is \"{}\"", query),
+        "path" => format!("at the path \"{}\"", query),
+        "any" => format!(
+            "with any attribute that {}",
+            ValueMatch::describe(query, options)
+        ),
+        _ => match canonical_field("secret-service", by) {
+            Some(attribute) => attribute_explanation(attribute, query, options),
+            None => {
+                return format!(
+                "cannot search by {}, expected user, target, service, schema, label, path or any",
+                by
+            )
+            }
+        },
+    };
+
+    let mut explanation = match (by.to_ascii_lowercase().as_str(), options.match_mode) {
+        ("path", _) => format!(
+            "lists every item in the collection holding the path, keeping the one {}",
+            items
+        ),
+        (by, MatchMode::Exact) if by != "label" && by != "any" => {
+            format!("searches every collection for items {}", items)
+        }
+        _ => format!(
+            "lists every item in every collection, keeping those {}",
+            items
+        ),
+    };
+    if options.skip_locked {
+        explanation.push_str(", skipping locked collections");
+    }
+    if options.parallel_collections {
+        explanation.push_str(", each collection on its own thread");
+    }
+    if let Some(time) = options.created_after {
+        explanation.push_str(&format!(
+            ", created after {} seconds since the epoch",
+            epoch_seconds(time)
+        ));
+    }
+    if let Some(time) = options.modified_before {
+        explanation.push_str(&format!(
+            ", last modified before {} seconds since the epoch",
+            epoch_seconds(time)
+        ));
+    }
+    if let Limit::Max(max) = options.limit {
+        explanation.push_str(&format!(", stopping after {}", max));
+    }
+    explanation
+}
+
+// Describes an attribute match, as attribute_match makes it.
+fn attribute_explanation(attribute: &str, query: &str, options: &SearchOptions) -> String {
+    format!(
+        "whose {} attribute {}",
+        attribute,
+        ValueMatch::describe(query, options)
+    )
+}
+
+// Maps the generic search parameters to secret-service searches,
+// connecting first when no connection is given.
+fn search_by(
+    ss: Option<&SecretService>,
+    by: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    search_collections(ss, item_match(by, query, options)?, options)
+}
+
+// Maps the generic search parameters to how items are matched.
+fn item_match<'a>(by: &str, query: &'a str, options: &SearchOptions) -> Result<ItemMatch<'a>> {
+    validate_query(query)?;
+
+    Ok(match by.to_ascii_lowercase().as_str() {
+        "label" => ItemMatch::Label(query),
+        "path" => ItemMatch::Path(query),
+        "any" => ItemMatch::AnyAttribute(ValueMatch::new(query, options)?),
+        _ => match canonical_field("secret-service", by) {
+            Some(attribute) => attribute_match(attribute, query, options)?,
+            None => {
                 return Err(ErrorCode::SearchError(
                     "Unexpected search by parameter".to_string(),
                 ))
             }
-        };
+        },
+    })
+}
 
-        search_items(by, query)
+// Passes every parameter to the DBus search as an attribute, any
+// attribute can be searched so none is unsupported.
+fn native_query(
+    ss: Option<&SecretService>,
+    params: &HashMap<String, String>,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    for (key, value) in params {
+        validate_query(key)?;
+        validate_query(value)?;
     }
+    let attributes = params
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+
+    search_collections(ss, ItemMatch::NativeAll(attributes), options)
 }
 
 /// Returns the items searched as a CredentialSearchResult.
@@ -42,67 +293,450 @@ impl CredentialSearchApi for SsCredentialSearch {
 /// 'username', 'application', 'service'. For most clients,
 /// this should be sufficient.
 pub fn search_items(by: &str, query: &str) -> CredentialSearchResult {
-    let mut count = 0;
-    let ss = match SecretService::connect(EncryptionType::Plain) {
-        Ok(connection) => connection,
-        Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
+    search_items_with_options(by, query, &SearchOptions::default()).map(|result| result.results)
+}
+
+/// Returns the items searched along with any warnings.
+///
+/// Behaves like [search_items], honoring the secret-service
+/// [options](SearchOptions). When `skip_locked` is set, each
+/// locked collection is skipped and reported as a warning.
+pub fn search_items_with_options(
+    by: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    validate_query(by)?;
+    validate_query(query)?;
+
+    search_collections(None, attribute_match(by, query, options)?, options)
+}
+
+/// Returns the items whose label matches the query.
+///
+/// The label is what Seahorse displays, but it isn't a searchable
+/// attribute in the DBus API, so every item is listed and filtered
+/// client-side. The query must match the label exactly.
+pub fn search_by_label(query: &str) -> CredentialSearchResult {
+    search_by_label_with_options(query, &SearchOptions::default()).map(|result| result.results)
+}
+
+/// Returns the items whose label matches the query along with any warnings.
+///
+/// Behaves like [search_by_label], honoring the secret-service
+/// [options](SearchOptions) as [search_items_with_options] does.
+pub fn search_by_label_with_options(
+    query: &str,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    validate_query(query)?;
+
+    search_collections(None, ItemMatch::Label(query), options)
+}
+
+/// Returns the items with any attribute value matching the query.
+///
+/// For when the schema isn't known, every item is listed and each
+/// of its attribute values compared to the query client-side. The
+/// matching items are returned with all of their attributes.
+pub fn search_by_any_value(query: &str) -> CredentialSearchResult {
+    search_by_any_value_with_options(query, &SearchOptions::default()).map(|result| result.results)
+}
+
+/// Returns the items with any attribute value matching the query along with any warnings.
+///
+/// Behaves like [search_by_any_value], comparing values with the
+/// options' [match_mode](SearchOptions::match_mode).
+pub fn search_by_any_value_with_options(
+    query: &str,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    validate_query(query)?;
+    let value_match = ValueMatch::new(query, options)?;
+
+    search_collections(None, ItemMatch::AnyAttribute(value_match), options)
+}
+
+// Rejects input that would otherwise reach D-Bus as an opaque failure.
+//
+// D-Bus strings can't hold NUL bytes, and the length is bounded so an
+// oversized query fails here rather than in the secret-service.
+fn validate_query(query: &str) -> Result<()> {
+    if query.contains('\0') {
+        return Err(ErrorCode::InvalidQuery(
+            "query contains a NUL byte".to_string(),
+        ));
+    }
+    if query.len() > MAX_QUERY_LEN {
+        return Err(ErrorCode::InvalidQuery(format!(
+            "query is longer than {} bytes",
+            MAX_QUERY_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+// How items are matched, by an attribute or several searched in DBus,
+// by an attribute, any attribute, label or path compared after listing
+// every item, or not at all, keeping every item listed.
+enum ItemMatch<'a> {
+    Native(&'a str, &'a str),
+    NativeAll(HashMap<&'a str, &'a str>),
+    Attribute(&'a str, ValueMatch),
+    AnyAttribute(ValueMatch),
+    Label(&'a str),
+    Path(&'a str),
+    All,
+}
+
+impl ItemMatch<'_> {
+    // Whether items are matched on the given attribute.
+    fn is_by(&self, attribute: &str) -> bool {
+        match self {
+            ItemMatch::Native(by, _) | ItemMatch::Attribute(by, _) => *by == attribute,
+            ItemMatch::NativeAll(attributes) => attributes.contains_key(attribute),
+            ItemMatch::AnyAttribute(_)
+            | ItemMatch::Label(_)
+            | ItemMatch::Path(_)
+            | ItemMatch::All => false,
+        }
+    }
+
+    // Whether an item listed from the collection matches, items
+    // found by the native search already do.
+    fn matches(&self, path: &str, attributes: &HashMap<String, String>, label: &str) -> bool {
+        match self {
+            ItemMatch::Native(_, _) | ItemMatch::NativeAll(_) | ItemMatch::All => true,
+            ItemMatch::Attribute(by, value_match) => attributes
+                .get(*by)
+                .map_or(false, |value| value_match.is_match(value)),
+            ItemMatch::AnyAttribute(value_match) => {
+                attributes.values().any(|value| value_match.is_match(value))
+            }
+            ItemMatch::Label(query) => label == *query,
+            ItemMatch::Path(query) => path == *query,
+        }
+    }
+}
+
+// Matches an attribute natively for exact matches, otherwise client-side.
+fn attribute_match<'a>(
+    by: &'a str,
+    query: &'a str,
+    options: &SearchOptions,
+) -> Result<ItemMatch<'a>> {
+    Ok(match options.match_mode {
+        MatchMode::Exact => ItemMatch::Native(by, query),
+        _ => ItemMatch::Attribute(by, ValueMatch::new(query, options)?),
+    })
+}
+
+// Runs the D-Bus call, retrying it once after a short delay if the
+// secret-service is unknown, as it is while the daemon is activated,
+// such as right after login.
+fn retry_service_unknown<T, E: Display>(
+    mut call: impl FnMut() -> std::result::Result<T, E>,
+) -> std::result::Result<T, E> {
+    match call() {
+        Err(err) if err.to_string().contains(SERVICE_UNKNOWN) => {
+            thread::sleep(SERVICE_RETRY_DELAY);
+            call()
+        }
+        result => result,
+    }
+}
+
+fn search_collections(
+    ss: Option<&SecretService>,
+    item_match: ItemMatch,
+    options: &SearchOptions,
+) -> Result<CredentialSearchResultWithWarnings> {
+    search_page(ss, item_match, options, 0, options.limit.max_items())
+}
+
+// Connects, unless given a connection, and lists the collections,
+// naming the locked ones by their label, or their path without one.
+fn health_check(ss: Option<&SecretService>) -> BackendHealth {
+    let connection;
+    let ss = match ss {
+        Some(ss) => ss,
+        None => {
+            connection =
+                match retry_service_unknown(|| SecretService::connect(EncryptionType::Plain)) {
+                    Ok(connection) => connection,
+                    Err(err) => return BackendHealth::new("secret-service", Some(err.to_string())),
+                };
+            &connection
+        }
     };
 
-    let collections = match ss.get_all_collections() {
+    let collections = match retry_service_unknown(|| ss.get_all_collections()) {
         Ok(collections) => collections,
-        Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
+        Err(err) => return BackendHealth::new("secret-service", Some(err.to_string())),
     };
+    let locked = collections
+        .iter()
+        .filter(|collection| collection.is_locked().unwrap_or(false))
+        .map(|collection| {
+            collection
+                .get_label()
+                .unwrap_or_else(|_| collection.collection_path.to_string())
+        })
+        .collect();
 
-    let mut search_map = HashMap::new();
-    search_map.insert(by, query);
+    BackendHealth {
+        collections: Some(collections.len()),
+        locked,
+        ..BackendHealth::new("secret-service", None)
+    }
+}
 
-    let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
-    for collection in collections {
-        let search_results = match collection.search_items(search_map.clone()) {
-            Ok(results) => results,
-            Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
-        };
+// Searches the collections for a page of the matching items, skipping
+// the first `offset`. Searched one at a time, each collection skips the
+// matches left to skip as it lists its items. Searched in parallel, each
+// keeps those up to the end of the page, and the ones before it are
+// dropped once they are combined.
+fn search_page(
+    ss: Option<&SecretService>,
+    item_match: ItemMatch,
+    options: &SearchOptions,
+    offset: usize,
+    max: usize,
+) -> Result<CredentialSearchResultWithWarnings> {
+    let connection;
+    let ss = match ss {
+        Some(ss) => ss,
+        None => {
+            connection =
+                match retry_service_unknown(|| SecretService::connect(EncryptionType::Plain)) {
+                    Ok(connection) => connection,
+                    Err(err) => return Err(ErrorCode::Connection(err.to_string())),
+                };
+            &connection
+        }
+    };
 
-        for result in search_results {
-            count += 1;
-            let attributes = match result.get_attributes() {
-                Ok(attributes) => attributes,
-                Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
-            };
-
-            let mut inner_map: HashMap<String, String> = HashMap::new();
-
-            for (key, value) in attributes {
-                // Seahorse will add an additional attribute with key "xdg:schema"
-                //
-                // This is negligible in practice and just specifies to type of credential
-                // for the keys and passwords application on gnome linux devices.
-                if key != *"xdg:schema".to_string() {
-                    inner_map.insert(key, value);
-                }
+    let collections = match retry_service_unknown(|| ss.get_all_collections()) {
+        Ok(collections) => collections,
+        Err(err) => return Err(ErrorCode::Connection(err.to_string())),
+    };
 
-                match result.get_label() {
-                    Ok(label) => inner_map.insert("label".to_string(), label),
-                    Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
-                };
+    // Each collection is searched on its own thread, sharing the
+    // connection, as every search is a blocking D-Bus round trip.
+    let mut truncated = false;
+    let mut unskipped = 0;
+    let collection_results: Vec<Result<CollectionResult>> = if options.parallel_collections {
+        unskipped = offset;
+        let end = offset.saturating_add(max);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = collections
+                .iter()
+                .map(|collection| {
+                    scope.spawn(|| search_collection(collection, &item_match, options, 0, end))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(ErrorCode::Unexpected(
+                            "Collection search thread panicked".to_string(),
+                        ))
+                    })
+                })
+                .collect()
+        })
+    } else {
+        let mut collection_results = Vec::new();
+        let mut matched = 0;
+        let mut skipped = 0;
+        for collection in &collections {
+            if matched >= max {
+                break;
+            }
+            if options
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
+            {
+                truncated = true;
+                break;
+            }
+            let skip = offset - skipped;
+            let collection_result = search_collection(collection, &item_match, options, skip, max);
+            if let Ok((results, _, collection_skipped)) = &collection_result {
+                matched += results.len();
+                skipped += collection_skipped;
+            }
+            collection_results.push(collection_result);
+        }
+        collection_results
+    };
 
-                outer_map.insert(count.to_string(), inner_map.clone());
+    let mut count = 0;
+    let mut warnings = Vec::new();
+    let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for collection_result in collection_results {
+        let (results, collection_warnings, _) = collection_result?;
+        warnings.extend(collection_warnings);
+        for inner_map in results {
+            if count == max {
+                break;
             }
+            if unskipped > 0 {
+                unskipped -= 1;
+                continue;
+            }
+            count += 1;
+            outer_map.insert(count.to_string(), inner_map);
         }
     }
 
-    if outer_map.is_empty() {
+    if outer_map.is_empty() && !truncated {
         Err(ErrorCode::NoResults)
     } else {
-        Ok(outer_map)
+        Ok(CredentialSearchResultWithWarnings {
+            results: outer_map,
+            warnings,
+            truncated,
+            ..Default::default()
+        })
+    }
+}
+
+// The seconds since the Unix epoch, as item times are stored, none
+// for a time before it.
+fn epoch_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// The matching items of a collection, the warnings searching it
+// and the number of matches skipped.
+type CollectionResult = (Vec<HashMap<String, String>>, Vec<String>, usize);
+
+fn search_collection(
+    collection: &Collection,
+    item_match: &ItemMatch,
+    options: &SearchOptions,
+    skip: usize,
+    max: usize,
+) -> Result<CollectionResult> {
+    let mut results = Vec::new();
+    let mut warnings = Vec::new();
+    let mut skipped = 0;
+
+    // A locked collection can't be used without unlocking it, so
+    // owned_only skips it too.
+    if (options.skip_locked || options.owned_only) && collection.is_locked().unwrap_or(false) {
+        let label = collection
+            .get_label()
+            .unwrap_or_else(|_| collection.collection_path.to_string());
+        warnings.push(format!("Skipped locked collection: {}", label));
+        return Ok((results, warnings, skipped));
+    }
+
+    // Item paths are nested under their collection's, so only
+    // the collection holding the path needs to be listed.
+    if let ItemMatch::Path(path) = item_match {
+        let prefix = format!("{}/", collection.collection_path.as_str());
+        if !path.starts_with(&prefix) {
+            return Ok((results, warnings, skipped));
+        }
+    }
+
+    let search_results = match item_match {
+        ItemMatch::Native(by, query) => collection.search_items(HashMap::from([(*by, *query)])),
+        ItemMatch::NativeAll(attributes) => collection.search_items(attributes.clone()),
+        _ => collection.get_all_items(),
+    };
+    let search_results = match search_results {
+        Ok(results) => results,
+        Err(err) => return Err(ErrorCode::SearchError(err.to_string())),
+    };
+
+    for result in search_results {
+        if results.len() == max {
+            break;
+        }
+        // An item that can't be read is left out and reported
+        // rather than failing the whole search.
+        let (attributes, label) = match (result.get_attributes(), result.get_label()) {
+            (Ok(attributes), Ok(label)) => (attributes, label),
+            (Err(err), _) | (_, Err(err)) => {
+                if skipped == skip {
+                    warnings.push(format!("Skipped item {}: {}", result.item_path, err));
+                }
+                continue;
+            }
+        };
+
+        let path = result.item_path.to_string();
+        if !item_match.matches(&path, &attributes, &label) {
+            continue;
+        }
+        let (created, modified) = match (result.get_created(), result.get_modified()) {
+            (Ok(created), Ok(modified)) => (created, modified),
+            (Err(err), _) | (_, Err(err)) => {
+                if skipped == skip {
+                    warnings.push(format!("Skipped item {}: {}", path, err));
+                }
+                continue;
+            }
+        };
+        if options
+            .created_after
+            .map_or(false, |time| created <= epoch_seconds(time))
+            || options
+                .modified_before
+                .map_or(false, |time| modified >= epoch_seconds(time))
+        {
+            continue;
+        }
+        // Matches on earlier pages are passed over once they're known to match.
+        if skipped < skip {
+            skipped += 1;
+            continue;
+        }
+        if options.raw {
+            results.push(attributes);
+            continue;
+        }
+
+        let mut inner_map: HashMap<String, String> = HashMap::new();
+
+        for (key, value) in attributes {
+            // Seahorse will add an additional attribute with key "xdg:schema"
+            //
+            // This is negligible in practice and just specifies to type of credential
+            // for the keys and passwords application on gnome linux devices.
+            // It is kept when searching by schema, to confirm the match.
+            if key != SCHEMA_ATTRIBUTE || item_match.is_by(SCHEMA_ATTRIBUTE) {
+                inner_map.insert(key, value);
+            }
+        }
+        inner_map.insert("label".to_string(), label);
+        inner_map.insert("path".to_string(), path);
+        inner_map.insert("created".to_string(), created.to_string());
+        inner_map.insert("modified".to_string(), modified.to_string());
+
+        results.push(inner_map);
     }
+
+    Ok((results, warnings, skipped))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{tests::generate_random_string, Error, Limit, List, Search};
+    use super::{
+        retry_service_unknown, search_by_any_value, search_by_label, search_items, EncryptionType,
+        SecretService, MAX_QUERY_LEN, SERVICE_UNKNOWN,
+    };
+    use crate::search::CredentialSearchResultExt;
+    use crate::{tests::generate_random_string, Error, Limit, List, MatchMode, Search};
     use keyring::{secret_service::SsCredential, Entry};
     use std::collections::HashSet;
+    use std::time::{Duration, Instant, SystemTime};
 
     #[test]
     fn test_search() {
@@ -122,13 +756,35 @@ mod tests {
             .downcast_ref()
             .expect("Not a Secret Service credential");
 
+        let path = result
+            .as_ref()
+            .ok()
+            .and_then(|result| result.get("1"))
+            .and_then(|inner| inner.get("path"))
+            .expect("No path in result");
+        assert!(path.starts_with("/org/freedesktop/secrets/collection/"));
+
+        let time = |field: &str| {
+            result
+                .as_ref()
+                .ok()
+                .and_then(|result| result.get("1"))
+                .and_then(|inner| inner.get(field))
+                .cloned()
+                .unwrap_or_default()
+        };
+
         let mut expected = format!("label: {}\n", actual.label);
+        expected.push_str(&format!("path: {}\n", path));
+        expected.push_str(&format!("created: {}\n", time("created")));
+        expected.push_str(&format!("modified: {}\n", time("modified")));
         expected.push_str("1\n");
         let attributes = &actual.attributes;
         for (key, value) in attributes {
             let attribute = format!("{}: {}\n", key, value);
             expected.push_str(attribute.as_str());
         }
+        expected.push_str(&format!("user: {}\n", attributes["username"]));
         let expected_set: HashSet<&str> = expected.lines().collect();
         let result_set: HashSet<&str> = list.lines().collect();
         assert_eq!(expected_set, result_set, "Search results do not match");
@@ -137,6 +793,259 @@ mod tests {
             .expect("Couldn't delete test-search");
     }
 
+    #[test]
+    fn test_search_by_label() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("search by label test password")
+            .expect("Failed to set password");
+
+        let actual: &SsCredential = entry
+            .get_credential()
+            .downcast_ref()
+            .expect("Not a Secret Service credential");
+
+        let result = search_by_label(&actual.label).expect("Failed to search by label");
+
+        assert_eq!(1, result.len());
+        assert!(result
+            .values()
+            .all(|inner| inner.get("label") == Some(&actual.label)));
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-by-label");
+    }
+
+    #[test]
+    fn test_search_by_path() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("search by path test password")
+            .expect("Failed to set password");
+
+        let search = Search::new().expect("Failed to build search");
+        let found = search
+            .by_service(&name)
+            .expect("Failed to search by service");
+        let path = found["1"]["path"].clone();
+        let result = search.by_path(&path);
+        let missing = search.by_path(&format!("{}0", path));
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-by-path");
+
+        let result = result.expect("Failed to search by path");
+        assert_eq!(1, result.len());
+        assert_eq!(Some(&name), result["1"].get("service"));
+        assert!(matches!(missing, Err(Error::NoResults)));
+    }
+
+    #[test]
+    fn test_search_by_any_value() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, "test-user").expect("Error creating searchable entry");
+        entry
+            .set_password("search by any value test password")
+            .expect("Failed to set password");
+
+        let result = search_by_any_value(&name).expect("Failed to search by any value");
+
+        assert_eq!(1, result.len());
+        assert!(result
+            .values()
+            .all(|inner| inner.get("username").map(String::as_str) == Some("test-user")));
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-by-any-value");
+    }
+
+    #[test]
+    fn test_connected_search() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("connected search test password")
+            .expect("Failed to set password");
+
+        let ss = SecretService::connect(EncryptionType::Dh).expect("Failed to connect");
+        let result = Search::default()
+            .with_secret_service(ss)
+            .by_service(&name)
+            .expect("Failed to search over connection");
+
+        assert_eq!(1, result.len());
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-connected-search");
+    }
+
+    #[test]
+    fn test_search_by_schema() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("search by schema test password")
+            .expect("Failed to set password");
+
+        let result = Search::default()
+            .by_schema("org.freedesktop.Secret.Generic")
+            .filter_results(|result| result.get("service") == Some(name.as_str()))
+            .expect("Failed to search by schema");
+
+        assert_eq!(1, result.len());
+        assert!(result
+            .values()
+            .all(|inner| inner.get("xdg:schema").map(String::as_str)
+                == Some("org.freedesktop.Secret.Generic")));
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-by-schema");
+    }
+
+    #[test]
+    fn test_search_raw() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("search raw test password")
+            .expect("Failed to set password");
+
+        let result = Search::default()
+            .by_raw("service", &name)
+            .expect("Failed to search raw attributes");
+
+        assert_eq!(1, result.len());
+        let inner = &result["1"];
+        assert!(inner.contains_key("xdg:schema"));
+        assert!(!inner.contains_key("label"));
+        assert!(!inner.contains_key("path"));
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-raw");
+    }
+
+    #[test]
+    fn test_deadline() {
+        let result = Search::default()
+            .deadline(Instant::now())
+            .by_verbose("service", &generate_random_string())
+            .expect("A search past its deadline should return what it found");
+
+        assert!(result.truncated);
+        assert!(result.results.is_empty());
+    }
+
+    #[test]
+    fn test_created_modified() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("created modified test password")
+            .expect("Failed to set password");
+        let hour = Duration::from_secs(60 * 60);
+
+        let recent = Search::default()
+            .ss_created_after(SystemTime::now() - hour)
+            .ss_modified_before(SystemTime::now() + hour)
+            .by_service(&name);
+        let future = Search::default()
+            .ss_created_after(SystemTime::now() + hour)
+            .by_service(&name);
+        let stale = Search::default()
+            .ss_modified_before(SystemTime::now() - hour)
+            .by_service(&name);
+
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-created-modified");
+        let recent = recent.expect("Failed to search recent items");
+        assert_eq!(1, recent.len());
+        assert!(recent["1"].contains_key("created") && recent["1"].contains_key("modified"));
+        assert!(matches!(future, Err(Error::NoResults)));
+        assert!(matches!(stale, Err(Error::NoResults)));
+    }
+
+    #[test]
+    fn test_parallel_collections() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("parallel collections test password")
+            .expect("Failed to set password");
+
+        let result = Search::default()
+            .parallel_collections(true)
+            .by_service(&name)
+            .expect("Failed to search collections in parallel");
+
+        assert_eq!(1, result.len());
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-parallel-collections");
+    }
+
+    #[test]
+    fn test_search_contains() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("search contains test password")
+            .expect("Failed to set password");
+
+        let result = Search::default()
+            .match_mode(MatchMode::Contains)
+            .by_service(&name[5..20].to_uppercase())
+            .expect("Failed to search by partial service");
+
+        assert_eq!(1, result.len());
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-contains");
+    }
+
+    #[test]
+    fn test_service_prefix_suffix() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("service prefix test password")
+            .expect("Failed to set password");
+
+        let search = Search::default();
+        let prefix = search.service_prefix(&name[..10]);
+        let suffix = search.service_suffix(&name[10..]);
+        let inner = search.service_prefix(&name[5..15]);
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-service-prefix-suffix");
+
+        assert_eq!(1, prefix.expect("Failed to search by prefix").len());
+        assert_eq!(1, suffix.expect("Failed to search by suffix").len());
+        assert!(matches!(inner, Err(Error::NoResults)));
+    }
+
+    #[test]
+    fn test_search_ignore_case() {
+        let name = generate_random_string();
+        let entry = Entry::new(&name, &name).expect("Error creating searchable entry");
+        entry
+            .set_password("search ignore case test password")
+            .expect("Failed to set password");
+
+        let exact = Search::default().by_user(&name.to_uppercase());
+        let result = Search::default()
+            .match_mode(MatchMode::IgnoreCase)
+            .by_user(&name.to_uppercase());
+        entry
+            .delete_password()
+            .expect("Couldn't delete test-search-ignore-case");
+
+        assert!(matches!(exact, Err(Error::NoResults)));
+        assert_eq!(1, result.expect("Failed to search ignoring case").len());
+    }
+
     #[test]
     fn test_max_result() {
         let name1 = generate_random_string();
@@ -193,6 +1102,60 @@ mod tests {
             .expect("Failed to delete password for entry4");
     }
 
+    #[test]
+    fn test_invalid_query() {
+        let nul = search_items("service", "foo\0bar");
+        let long = search_items("service", &"a".repeat(MAX_QUERY_LEN + 1));
+
+        assert!(matches!(nul.unwrap_err(), Error::InvalidQuery(_)));
+        assert!(matches!(long.unwrap_err(), Error::InvalidQuery(_)));
+    }
+
+    #[test]
+    fn test_retry_service_unknown() {
+        let mut calls = 0;
+        let result = retry_service_unknown(|| {
+            calls += 1;
+            match calls {
+                1 => Err(format!("{}: not activated yet", SERVICE_UNKNOWN)),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(Ok(2), result);
+
+        let mut calls = 0;
+        let result: std::result::Result<(), String> = retry_service_unknown(|| {
+            calls += 1;
+            Err(format!("{}: still not activated", SERVICE_UNKNOWN))
+        });
+        assert!(result.is_err());
+        assert_eq!(2, calls);
+
+        let mut calls = 0;
+        let result: std::result::Result<(), String> = retry_service_unknown(|| {
+            calls += 1;
+            Err("org.freedesktop.DBus.Error.AccessDenied".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(1, calls);
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(
+            "secret-service: searches every collection for items whose username attribute is \"foo\"",
+            Search::default().explain("user", "foo")
+        );
+        assert_eq!(
+            "secret-service: lists every item in every collection, keeping those \
+            whose application attribute contains \"foo\", ignoring case, skipping locked collections",
+            Search::default()
+                .match_mode(MatchMode::Contains)
+                .skip_locked(true)
+                .explain("target", "foo")
+        );
+    }
+
     #[test]
     fn no_results() {
         let name = generate_random_string();
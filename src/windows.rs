@@ -1,23 +1,31 @@
-use regex::Regex;
 use std::collections::HashMap;
-use windows_sys::Win32::Foundation::{FILETIME, SYSTEMTIME};
+use std::string::FromUtf16Error;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_FOUND, FILETIME, SYSTEMTIME};
 use windows_sys::Win32::Security::Credentials::{
-    CredEnumerateW, CredFree, CREDENTIALW, CRED_ENUMERATE_ALL_CREDENTIALS, CRED_PERSIST, CRED_TYPE,
+    CredEnumerateW, CredFree, CREDENTIALW, CRED_ENUMERATE_ALL_CREDENTIALS, CRED_PERSIST,
+    CRED_PERSIST_ENTERPRISE, CRED_TYPE,
 };
 use windows_sys::Win32::Storage::FileSystem::FileTimeToLocalFileTime;
 use windows_sys::Win32::System::Time::{LocalFileTimeToLocalSystemTime, TIME_ZONE_INFORMATION};
 
 use super::error::{Error as ErrorCode, Result};
-use super::search::{CredentialSearch, CredentialSearchApi, CredentialSearchResult};
+use super::search::{
+    base64, filter_native, BackendHealth, CredentialSearch, CredentialSearchApi,
+    CredentialSearchResult, CredentialSearchResultWithWarnings, Limit, MatchMode, SearchOptions,
+    SearchPlan, ValueMatch, NO_USER,
+};
 
+// Indexed by SYSTEMTIME's wDayOfWeek, which is 0 for Sunday.
 static DAYS: [&str; 7] = [
+    "Sunday",
     "Monday",
     "Tuesday",
     "Wednesday",
     "Thursday",
     "Friday",
     "Saturday",
-    "Sunday",
 ];
 static MONTHS: [&str; 12] = [
     "January",
@@ -40,11 +48,15 @@ static MONTHS: [&str; 12] = [
 pub struct WinCredential {
     pub username: String,
     pub target_name: String,
+    pub raw_target_name: String,
+    pub raw_username: String,
     pub target_alias: String,
     pub comment: String,
     pub cred_type: CRED_TYPE,
     pub last_written: HumanTime,
     pub persist: CRED_PERSIST,
+    /// The decoded blob, when it was asked for with `include_blob`.
+    pub blob: Option<String>,
 }
 
 pub struct HumanTime {
@@ -70,10 +82,34 @@ impl std::fmt::Display for HumanTime {
 // Type matching for search types
 enum WinSearchType {
     Target,
-    Service,
+    RawTarget,
     User,
+    Blob,
 }
 
+// The fields of a result, which a native query filters on, as listed
+// and with the raw option.
+const FIELDS: &[&str] = &[
+    "Comment",
+    "User",
+    "Type",
+    "Last Written",
+    "Persist",
+    "Target",
+    "Raw Target",
+    "Blob",
+];
+const RAW_FIELDS: &[&str] = &[
+    "TargetName",
+    "UserName",
+    "TargetAlias",
+    "Comment",
+    "Type",
+    "Persist",
+    "LastWritten",
+    "CredentialBlob",
+];
+
 pub struct WinCredentialSearch {}
 
 /// Returns an instance of the Windows credential search.
@@ -95,71 +131,319 @@ impl CredentialSearchApi for WinCredentialSearch {
     ///     let search = keyring_search::Search::new().unwrap();
     ///     let results = search.by_user("Mr. Foo Bar");
     fn by(&self, by: &str, query: &str) -> CredentialSearchResult {
+        self.by_with_options(by, query, &SearchOptions::default())
+            .map(|result| result.results)
+    }
+
+    /// Credentials with an unexpected type or persistence, or with
+    /// invalid UTF-16 when `strict_utf16` is set, are left out of
+    /// the results and reported as warnings, with empty results rather
+    /// than [NoResults](super::Error::NoResults) when nothing else matched.
+    fn by_with_options(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        self.by_page(by, query, options, 0, options.limit.max_items())
+    }
+
+    /// The matches before the page are skipped as the credentials are
+    /// converted, along with the warnings for the credentials among them.
+    fn by_page(
+        &self,
+        by: &str,
+        query: &str,
+        options: &SearchOptions,
+        offset: usize,
+        max: usize,
+    ) -> Result<CredentialSearchResultWithWarnings> {
         let mut count = 0;
-        let results = match search_type(by, query) {
-            Ok(results) => results,
-            Err(err) => return Err(err),
-        };
+        let mut skipped = 0;
+        let (results, mut warnings) = search_type(by, query, options)?;
 
         let mut outer_map: HashMap<String, HashMap<String, String>> = HashMap::new();
         for result in results {
+            if count == max {
+                break;
+            }
+            if options.raw {
+                if skipped < offset {
+                    skipped += 1;
+                    continue;
+                }
+                count += 1;
+                outer_map.insert(count.to_string(), raw_fields(&result));
+                continue;
+            }
+            let (cred_type, persist) = match (
+                match_cred_type(result.cred_type),
+                match_persist_type(result.persist),
+            ) {
+                (Ok(cred_type), Ok(persist)) => (cred_type, persist),
+                (Err(err), _) | (_, Err(err)) => {
+                    if skipped == offset {
+                        warnings.push(format!(
+                            "Skipped credential {}: {}",
+                            result.target_name, err
+                        ));
+                    }
+                    continue;
+                }
+            };
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+
             count += 1;
             let mut inner_map: HashMap<String, String> = HashMap::new();
 
             inner_map.insert("Comment".to_string(), result.comment.clone());
             inner_map.insert("User".to_string(), result.username.clone());
-            inner_map.insert("Type".to_string(), match_cred_type(result.cred_type)?);
+            inner_map.insert("Type".to_string(), cred_type);
             inner_map.insert("Last Written".to_string(), result.last_written.to_string());
-            inner_map.insert("Persist".to_string(), match_persist_type(result.persist)?);
+            inner_map.insert("Persist".to_string(), persist);
             inner_map.insert("Target".to_string(), result.target_name.to_string());
+            inner_map.insert("Raw Target".to_string(), result.raw_target_name.to_string());
+            if let Some(blob) = result.blob {
+                inner_map.insert("Blob".to_string(), blob);
+            }
 
             outer_map.insert(count.to_string(), inner_map);
         }
 
-        Ok(outer_map)
+        // Skipped credentials may have matched, so they're reported
+        // rather than hidden behind NoResults.
+        if outer_map.is_empty() && warnings.is_empty() {
+            return Err(ErrorCode::NoResults);
+        }
+
+        Ok(CredentialSearchResultWithWarnings {
+            results: outer_map,
+            warnings,
+            ..Default::default()
+        })
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "windows"
     }
+
+    fn explain(&self, by: &str, query: &str, options: &SearchOptions) -> String {
+        let field = match by.to_ascii_lowercase().as_str() {
+            "target" | "service" => "target name, without its type prefix",
+            "raw_target" => "raw target name",
+            "user" => "user name",
+            "blob" if options.include_blob => "decoded blob",
+            "blob" => return "cannot search by blob without include_blob".to_string(),
+            _ => {
+                return format!(
+                    "cannot search by {}, expected target, raw_target, service, user or blob",
+                    by
+                )
+            }
+        };
+
+        let mut explanation = String::from("enumerates every credential");
+        if let Some(cred_types) = &options.cred_types {
+            explanation.push_str(&format!(" of the types {:?}", cred_types));
+        }
+        if options.strict_utf16 {
+            explanation.push_str(" with valid UTF-16");
+        }
+        if options.owned_only {
+            explanation.push_str(" not persisted for the enterprise");
+        }
+        if let Some(timeout) = options.enumeration_timeout {
+            explanation.push_str(&format!(" within {:?}", timeout));
+        }
+        explanation.push_str(&format!(
+            ", keeping those whose {} {}",
+            field,
+            ValueMatch::describe_regex_or_tokens(query, options)
+        ));
+        if let Limit::Max(max) = options.limit {
+            explanation.push_str(&format!(", stopping after {}", max));
+        }
+        explanation
+    }
+
+    fn plan(&self, by: &str, query: &str, options: &SearchOptions) -> SearchPlan {
+        let scope = match &options.cred_types {
+            Some(cred_types) => format!("every credential of the types {:?}", cred_types),
+            None => "every credential".to_string(),
+        };
+        SearchPlan::new(self, by, query, options, &scope)
+    }
+
+    fn all(&self, options: &SearchOptions) -> Result<CredentialSearchResultWithWarnings> {
+        // An empty regex matches every raw target name, whatever the match mode.
+        let options = SearchOptions {
+            match_mode: MatchMode::Regex,
+            ..options.clone()
+        };
+        self.by_with_options("raw_target", "", &options)
+    }
+
+    /// Credentials have no attributes to query, so each parameter
+    /// names a field of the results that must equal its value.
+    fn native_query(
+        &self,
+        params: &HashMap<String, String>,
+        options: &SearchOptions,
+    ) -> Result<CredentialSearchResultWithWarnings> {
+        let fields = match options.raw {
+            true => RAW_FIELDS,
+            false => FIELDS,
+        };
+        filter_native(self, params, fields, options)
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        Ok(match enumerate_credentials(&SearchOptions::default()) {
+            Ok((credentials, _)) => BackendHealth {
+                credentials: Some(credentials.len()),
+                ..BackendHealth::new(self.backend_name(), None)
+            },
+            Err(err) => BackendHealth::new(self.backend_name(), Some(err.to_string())),
+        })
+    }
+}
+
+// The credential's fields named after the CREDENTIALW members, with
+// the raw target and user names, and the type and persistence numeric.
+fn raw_fields(credential: &WinCredential) -> HashMap<String, String> {
+    let mut fields = HashMap::from([
+        ("TargetName".to_string(), credential.raw_target_name.clone()),
+        ("UserName".to_string(), credential.raw_username.clone()),
+        ("TargetAlias".to_string(), credential.target_alias.clone()),
+        ("Comment".to_string(), credential.comment.clone()),
+        ("Type".to_string(), credential.cred_type.to_string()),
+        ("Persist".to_string(), credential.persist.to_string()),
+        (
+            "LastWritten".to_string(),
+            credential.last_written.to_string(),
+        ),
+    ]);
+    if let Some(blob) = &credential.blob {
+        fields.insert("CredentialBlob".to_string(), blob.clone());
+    }
+    fields
 }
 
 // Match search type
-fn search_type(by: &str, query: &str) -> Result<Vec<WinCredential>> {
+fn search_type(
+    by: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<(Vec<WinCredential>, Vec<String>)> {
     let search_type = match by.to_ascii_lowercase().as_str() {
         "target" => WinSearchType::Target,
-        "service" => WinSearchType::Service,
+        "raw_target" => WinSearchType::RawTarget,
+        // Credentials are named by their target, keyring-rs's `{user}.{service}`,
+        // while the comment only describes them.
+        "service" => WinSearchType::Target,
         "user" => WinSearchType::User,
+        "blob" if options.include_blob => WinSearchType::Blob,
+        "blob" => {
+            return Err(ErrorCode::SearchError(
+                "Searching by blob needs include_blob".to_string(),
+            ))
+        }
         _ => {
             return Err(ErrorCode::SearchError(
-                "Invalid search parameter, not Target, Service, or User".to_string(),
+                "Invalid search parameter, not Target, Raw Target, Service, User, or Blob"
+                    .to_string(),
             ))
         }
     };
 
-    search(&search_type, query)
+    search(&search_type, query, options)
 }
 // Perform search, can return a regex error if the search parameter is invalid
-fn search(search_type: &WinSearchType, search_parameter: &str) -> Result<Vec<WinCredential>> {
-    let credentials = get_all_credentials();
+fn search(
+    search_type: &WinSearchType,
+    search_parameter: &str,
+    options: &SearchOptions,
+) -> Result<(Vec<WinCredential>, Vec<String>)> {
+    let (credentials, warnings) = enumerate_credentials(options)?;
 
-    let re = format!(r#"(?i){}"#, search_parameter);
-    let regex = match Regex::new(re.as_str()) {
-        Ok(regex) => regex,
-        Err(err) => return Err(ErrorCode::SearchError(format!("Regex Error, {}", err))),
-    };
+    let value_match = ValueMatch::regex_or_tokens(search_parameter, options)?;
 
     let mut results = Vec::new();
     for credential in credentials {
+        if let Some(cred_types) = &options.cred_types {
+            if !cred_types.contains(&credential.cred_type) {
+                continue;
+            }
+        }
+        // Enterprise credentials roam with the domain profile.
+        if options.owned_only && credential.persist == CRED_PERSIST_ENTERPRISE {
+            continue;
+        }
         let haystack = match search_type {
             WinSearchType::Target => &credential.target_name,
-            WinSearchType::Service => &credential.comment,
+            WinSearchType::RawTarget => &credential.raw_target_name,
             WinSearchType::User => &credential.username,
+            WinSearchType::Blob => credential.blob.as_deref().unwrap_or_default(),
         };
-        if regex.is_match(haystack) {
+        if value_match.is_match(haystack) {
             results.push(credential);
         }
     }
-    if results.is_empty() {
+    // Credentials skipped for invalid UTF-16 may have matched.
+    if results.is_empty() && warnings.is_empty() {
         Err(ErrorCode::NoResults)
     } else {
-        Ok(results)
+        Ok((results, warnings))
+    }
+}
+
+// Strips the `type:name=` prefix Windows prepends to target names,
+// such as `LegacyGeneric:target=`. A name without one, even if it
+// contains '=' as in `key=value`, is returned whole.
+fn strip_type_prefix(raw_target_name: &str) -> &str {
+    let is_word = |part: &str| {
+        !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+
+    match raw_target_name.split_once('=') {
+        Some((prefix, target_name))
+            if prefix
+                .split_once(':')
+                .map_or(false, |(kind, name)| is_word(kind) && is_word(name)) =>
+        {
+            target_name
+        }
+        _ => raw_target_name,
+    }
+}
+
+// Enumerates the credentials, on another thread when there is a timeout
+// so the search can give up on it. CredEnumerateW can't be cancelled,
+// so a thread that times out is left to finish on its own.
+fn enumerate_credentials(options: &SearchOptions) -> Result<(Vec<WinCredential>, Vec<String>)> {
+    let timeout = match options.enumeration_timeout {
+        Some(timeout) => timeout,
+        None => return get_all_credentials(options.strict_utf16, options.include_blob),
+    };
+
+    let (strict_utf16, include_blob) = (options.strict_utf16, options.include_blob);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone once the search has timed out.
+        let _ = sender.send(get_all_credentials(strict_utf16, include_blob));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            Err(ErrorCode::SearchError("enumeration timed out".to_string()))
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(ErrorCode::Unexpected(
+            "Credential enumeration thread panicked".to_string(),
+        )),
     }
 }
 
@@ -167,53 +451,122 @@ fn search(search_type: &WinSearchType, search_parameter: &str) -> Result<Vec<Win
 ///
 /// In Windows the target name is prepended with the credential type by default
 /// i.e. LegacyGeneric:target=Example Target Name.
-/// The type is stripped for string matching.
+/// The type is stripped for string matching, the unstripped
+/// name is kept as the raw target name.
 /// There is no guarantee that the entries wil be in the same order as in
 /// Windows Credential Manager.
-fn get_all_credentials() -> Vec<WinCredential> {
+///
+/// Invalid UTF-16 is replaced with U+FFFD, unless `strict_utf16` is set,
+/// in which case the credential is skipped and reported as a warning.
+/// The blob is only read when `include_blob` is set.
+/// Fails with [Connection](ErrorCode::Connection) if the credentials
+/// can't be enumerated.
+fn get_all_credentials(
+    strict_utf16: bool,
+    include_blob: bool,
+) -> Result<(Vec<WinCredential>, Vec<String>)> {
     let mut entries: Vec<WinCredential> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
     let mut count = 0;
     let mut credentials_ptr = std::ptr::null_mut();
 
-    unsafe {
+    let enumerated = unsafe {
         CredEnumerateW(
             std::ptr::null(),
             CRED_ENUMERATE_ALL_CREDENTIALS,
             &mut count,
             &mut credentials_ptr,
-        );
+        )
+    };
+    if enumerated == 0 {
+        // An empty credential manager is reported as not found.
+        return match unsafe { GetLastError() } {
+            ERROR_NOT_FOUND => Ok((entries, warnings)),
+            code => Err(ErrorCode::Connection(format!(
+                "CredEnumerateW failed with error code {}",
+                code
+            ))),
+        };
     }
 
-    let credentials =
-        unsafe { std::slice::from_raw_parts::<&CREDENTIALW>(credentials_ptr as _, count as usize) };
+    // A slice can't be built from a null pointer, even an empty one.
+    if credentials_ptr.is_null() {
+        return Ok((entries, warnings));
+    }
+
+    // The entries are read as raw pointers, as a null reference would be UB.
+    let credentials = unsafe {
+        std::slice::from_raw_parts::<*const CREDENTIALW>(credentials_ptr as _, count as usize)
+    };
+
+    for &credential_ptr in credentials {
+        if credential_ptr.is_null() {
+            continue;
+        }
+        let credential = unsafe { &*credential_ptr };
+        let raw_target_name = unsafe { from_wstr(credential.TargetName) };
+        if strict_utf16 {
+            let fields = [
+                credential.TargetName,
+                credential.UserName,
+                credential.TargetAlias,
+                credential.Comment,
+            ];
+            if let Some(err) = fields
+                .into_iter()
+                .find_map(|field| unsafe { from_wstr_strict(field) }.err())
+            {
+                warnings.push(format!("Skipped credential {}: {}", raw_target_name, err));
+                continue;
+            }
+        }
 
-    for credential in credentials {
-        let target_name = unsafe { from_wstr(credential.TargetName) };
         // By default the target names are prepended with the credential type
-        // i.e. LegacyGeneric:target=Example Target Name. This is where
-        // The '=' is indexed to strip the prepended type
-        let index = target_name.find('=').unwrap_or(0);
-        let target_name = target_name[index + 1..].to_string();
+        // i.e. LegacyGeneric:target=Example Target Name. The prepended
+        // type is stripped, the raw target name is kept as some tools
+        // store meaningful info in the prefix.
+        let target_name = strip_type_prefix(&raw_target_name).to_string();
 
-        let username = if unsafe { from_wstr(credential.UserName) }.is_empty() {
-            String::from("NO USER")
+        let raw_username = unsafe { from_wstr(credential.UserName) };
+        let username = if raw_username.is_empty() {
+            NO_USER.to_string()
         } else {
-            unsafe { from_wstr(credential.UserName) }
+            raw_username.clone()
         };
         let target_alias = unsafe { from_wstr(credential.TargetAlias) };
         let comment = unsafe { from_wstr(credential.Comment) };
         let cred_type = credential.Type;
-        let last_written = unsafe { get_last_written(credential.LastWritten) };
+        let last_written = match unsafe { get_last_written(credential.LastWritten) } {
+            Ok(last_written) => last_written,
+            Err(err) => {
+                warnings.push(format!("Skipped credential {}: {}", raw_target_name, err));
+                continue;
+            }
+        };
         let persist = credential.Persist;
+        let blob = if include_blob && !credential.CredentialBlob.is_null() {
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    credential.CredentialBlob,
+                    credential.CredentialBlobSize as usize,
+                )
+            };
+            Some(decode_blob(bytes))
+        } else {
+            None
+        };
 
         entries.push(WinCredential {
             username,
             target_name,
+            raw_target_name,
+            raw_username,
             target_alias,
             comment,
             cred_type,
             last_written,
             persist,
+            blob,
         });
     }
 
@@ -224,22 +577,48 @@ fn get_all_credentials() -> Vec<WinCredential> {
         >(credentials_ptr))
     };
 
-    entries
+    Ok((entries, warnings))
 }
 
-unsafe fn get_last_written(last_written: FILETIME) -> HumanTime {
+// Converts the time to local time, failing rather than naming a
+// zeroed time when either conversion fails.
+unsafe fn get_last_written(last_written: FILETIME) -> Result<HumanTime> {
     let mut local_filetime: FILETIME = std::mem::zeroed();
     let mut system_time: SYSTEMTIME = std::mem::zeroed();
     let local: TIME_ZONE_INFORMATION = std::mem::zeroed();
-    FileTimeToLocalFileTime(&last_written, &mut local_filetime as *mut FILETIME);
-    LocalFileTimeToLocalSystemTime(&local, &local_filetime, &mut system_time as *mut SYSTEMTIME);
+    let converted = FileTimeToLocalFileTime(&last_written, &mut local_filetime as *mut FILETIME)
+        != 0
+        && LocalFileTimeToLocalSystemTime(
+            &local,
+            &local_filetime,
+            &mut system_time as *mut SYSTEMTIME,
+        ) != 0;
+    if !converted {
+        return Err(ErrorCode::Unexpected(format!(
+            "Couldn't convert the last written time, error code {}",
+            GetLastError()
+        )));
+    }
+
+    Ok(human_time(&system_time))
+}
+
+// Names the day and month of the time, as "Unknown" when out of range.
+fn human_time(system_time: &SYSTEMTIME) -> HumanTime {
+    let name = |names: &[&str], index: Option<usize>| {
+        index
+            .and_then(|index| names.get(index))
+            .unwrap_or(&"Unknown")
+            .to_string()
+    };
+
     HumanTime {
         hour: system_time.wHour,
         minute: system_time.wMinute,
         second: system_time.wSecond,
-        day_of_week: DAYS[system_time.wDayOfWeek as usize - 1].to_string(),
+        day_of_week: name(&DAYS, Some(system_time.wDayOfWeek as usize)),
         day: system_time.wDay,
-        month: MONTHS[system_time.wMonth as usize - 1].to_string(),
+        month: name(&MONTHS, (system_time.wMonth as usize).checked_sub(1)),
         year: system_time.wYear,
     }
 }
@@ -268,60 +647,86 @@ fn match_persist_type(credential: u32) -> Result<String> {
     }
 }
 
+// Decodes a credential blob as UTF-16, as keyring-rs writes passwords,
+// dropping any terminating NULs. Blobs that aren't UTF-16 text, such as
+// odd-sized ones or ones decoding to control characters, are binary
+// and returned as base64.
+fn decode_blob(bytes: &[u8]) -> String {
+    if bytes.len() % 2 == 0 {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        if let Ok(text) = String::from_utf16(&units) {
+            let text = text.trim_end_matches('\0');
+            if !text.chars().any(char::is_control) {
+                return text.to_string();
+            }
+        }
+    }
+    base64(bytes)
+}
+
 unsafe fn from_wstr(ws: *const u16) -> String {
-    // null pointer case, return empty string
+    String::from_utf16_lossy(wstr_slice(ws))
+}
+
+// Like from_wstr, but fails on invalid UTF-16 rather than replacing it.
+unsafe fn from_wstr_strict(ws: *const u16) -> std::result::Result<String, FromUtf16Error> {
+    String::from_utf16(wstr_slice(ws))
+}
+
+unsafe fn wstr_slice<'a>(ws: *const u16) -> &'a [u16] {
+    // null pointer case, return empty slice
     if ws.is_null() {
-        return String::new();
+        return &[];
     }
     // this code from https://stackoverflow.com/a/48587463/558006
     let len = (0..).take_while(|&i| *ws.offset(i) != 0).count();
     if len == 0 {
-        return String::new();
+        return &[];
     }
-    let slice = std::slice::from_raw_parts(ws, len);
-    String::from_utf16_lossy(slice)
+    std::slice::from_raw_parts(ws, len)
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
     use std::iter::once;
+    use std::time::Duration;
 
     use byteorder::{ByteOrder, LittleEndian};
-    use windows_sys::Win32::Foundation::FILETIME;
+    use windows_sys::Win32::Foundation::{FILETIME, SYSTEMTIME};
     use windows_sys::Win32::Security::Credentials::{
         CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CREDENTIAL_ATTRIBUTEW,
-        CRED_FLAGS, CRED_PERSIST_ENTERPRISE, CRED_TYPE_GENERIC,
+        CRED_FLAGS, CRED_PERSIST_ENTERPRISE, CRED_TYPE_DOMAIN_PASSWORD, CRED_TYPE_GENERIC,
     };
 
     use crate::{tests::generate_random_string, Search};
     use crate::{Error, Limit, List};
 
-    use super::{get_last_written, match_cred_type, match_persist_type};
+    use super::{
+        decode_blob, from_wstr, from_wstr_strict, get_last_written, human_time, match_cred_type,
+        match_persist_type, strip_type_prefix,
+    };
 
     fn to_wstr(s: &str) -> Vec<u16> {
         s.encode_utf16().chain(once(0)).collect()
     }
 
     fn delete_credential(name: &str) {
-        unsafe {
-            CredDeleteW(
-                to_wstr(&name).as_ptr(),
-                CRED_TYPE_GENERIC,
-                CRED_TYPE_GENERIC,
-            )
-        };
+        unsafe { CredDeleteW(to_wstr(name).as_ptr(), CRED_TYPE_GENERIC, CRED_TYPE_GENERIC) };
     }
 
     fn create_credential(name: &str, user: Option<&str>) {
-        let mut user = if user == None {
-            to_wstr(&name)
-        } else {
-            to_wstr(user.unwrap())
-        };
-        let mut target_name = to_wstr(&name);
-        let mut target_alias = to_wstr(&name);
-        let mut comment = to_wstr(&name);
+        create_credential_wide(name, to_wstr(user.unwrap_or(name)));
+    }
+
+    // Takes the user as UTF-16, which may be invalid.
+    fn create_credential_wide(name: &str, mut user: Vec<u16>) {
+        let mut target_name = to_wstr(name);
+        let mut target_alias = to_wstr(name);
+        let mut comment = to_wstr(name);
         let last_written = FILETIME {
             dwLowDateTime: 0,
             dwHighDateTime: 0,
@@ -371,14 +776,19 @@ mod tests {
         };
 
         let expected = format!(
-            "1\nTarget: {}\nLast Written: {}\nType: {}\nPersist: {}\nUser: {}\nComment: {}\n",
+            "1\nTarget: {}\nRaw Target: {}\nLast Written: {}\nType: {}\nPersist: {}\nUser: {}\nComment: {}\nservice: {}\nuser: {}\ntarget: {}\n",
+            name,
             name,
-            unsafe { get_last_written(last_written_filetime) },
+            unsafe { get_last_written(last_written_filetime) }
+                .expect("Failed to convert last written time"),
             match_cred_type(CRED_TYPE_GENERIC).expect("Failed to match expected cred type"),
             match_persist_type(CRED_PERSIST_ENTERPRISE)
                 .expect("Failed to match expected persist type"),
             name,
             name,
+            name,
+            name,
+            name,
         );
 
         let search_result = match by.to_ascii_lowercase().as_str() {
@@ -391,6 +801,10 @@ mod tests {
             "service" => Search::new()
                 .expect("Error creating test search")
                 .by_service(&name.clone()),
+            "raw_target" => Search::new()
+                .expect("Error creating test search")
+                .by_verbose("raw_target", &name.clone())
+                .map(|result| result.results),
             _ => panic!("Unexpected search by parameter"),
         };
 
@@ -418,6 +832,11 @@ mod tests {
         test_search("target")
     }
 
+    #[test]
+    fn test_search_by_raw_target() {
+        test_search("raw_target")
+    }
+
     #[test]
     fn test_max_result() {
         let name1 = generate_random_string();
@@ -455,8 +874,196 @@ mod tests {
         // one credential, we count the amount of lines returned.
         // To adjust this test: add extra random names, create
         // more credentials with test-user, adjust the limit and
-        // make the assert number a multiple of 7.
-        assert_eq!(7, lines);
+        // make the assert number a multiple of 11.
+        assert_eq!(11, lines);
+    }
+
+    #[test]
+    fn test_cred_types() {
+        let name = generate_random_string();
+        create_credential(&name, None);
+
+        let generic = Search::new()
+            .expect("Error creating test search")
+            .cred_types(&[CRED_TYPE_GENERIC])
+            .by_target(&name);
+        let domain = Search::new()
+            .expect("Error creating test search")
+            .cred_types(&[CRED_TYPE_DOMAIN_PASSWORD])
+            .by_target(&name);
+
+        delete_credential(&name);
+        assert_eq!(1, generic.expect("Generic credential not found").len());
+        assert!(matches!(domain.unwrap_err(), Error::NoResults));
+    }
+
+    #[test]
+    fn test_owned_only() {
+        let name = generate_random_string();
+        create_credential(&name, None);
+
+        let search = Search::new().expect("Error creating test search");
+        let all = search.by_target(&name);
+        let owned = search.owned_only(true).by_target(&name);
+
+        // The test credentials are persisted for the enterprise.
+        delete_credential(&name);
+        assert_eq!(1, all.expect("Credential not found").len());
+        assert!(matches!(owned.unwrap_err(), Error::NoResults));
+    }
+
+    #[test]
+    fn test_enumeration_timeout() {
+        let name = generate_random_string();
+        create_credential(&name, None);
+
+        let result = Search::new()
+            .expect("Error creating test search")
+            .enumeration_timeout(Duration::from_secs(60))
+            .by_target(&name);
+
+        delete_credential(&name);
+        assert_eq!(1, result.expect("Credential not found").len());
+    }
+
+    #[test]
+    fn test_search_by_blob() {
+        let name = generate_random_string();
+        create_credential(&name, None);
+
+        let search = Search::new().expect("Error creating test search");
+        let without = search.by_verbose("blob", "^password$");
+        let result = search.include_blob(true).by_verbose("blob", "^password$");
+        delete_credential(&name);
+
+        assert!(matches!(without, Err(Error::SearchError(_))));
+        let result = result.expect("Failed to search by blob").results;
+        assert!(result.values().any(|fields| {
+            fields.get("Target") == Some(&name)
+                && fields.get("Blob").map(String::as_str) == Some("password")
+        }));
+    }
+
+    #[test]
+    fn test_decode_blob() {
+        let text: Vec<u8> = "mail@example.com\0"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!("mail@example.com", decode_blob(&text));
+        assert_eq!("", decode_blob(&[]));
+        // Odd-sized, a lone surrogate and a control character are binary.
+        assert_eq!("YQ==", decode_blob(b"a"));
+        assert_eq!("ANg=", decode_blob(&[0x00, 0xd8]));
+        assert_eq!("AQA=", decode_blob(&[0x01, 0x00]));
+    }
+
+    #[test]
+    fn test_human_time_day_of_week() {
+        let days = [
+            "Sunday",
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+        ];
+        for (day_of_week, day) in days.iter().enumerate() {
+            // 7 to 13 April 2024 ran from Sunday to Saturday.
+            let system_time = SYSTEMTIME {
+                wYear: 2024,
+                wMonth: 4,
+                wDayOfWeek: day_of_week as u16,
+                wDay: 7 + day_of_week as u16,
+                wHour: 9,
+                wMinute: 5,
+                wSecond: 0,
+                wMilliseconds: 0,
+            };
+            let time = human_time(&system_time);
+            assert_eq!(*day, time.day_of_week);
+            assert_eq!(
+                format!("{}, {} April, 2024 at 09:05:00", day, 7 + day_of_week),
+                time.to_string()
+            );
+        }
+
+        let zeroed: SYSTEMTIME = unsafe { std::mem::zeroed() };
+        assert_eq!("Unknown", human_time(&zeroed).month);
+    }
+
+    #[test]
+    fn test_last_written_out_of_range() {
+        // Times with the high bit set are past what SYSTEMTIME can hold.
+        let last_written = FILETIME {
+            dwLowDateTime: u32::MAX,
+            dwHighDateTime: u32::MAX,
+        };
+
+        assert!(matches!(
+            unsafe { get_last_written(last_written) },
+            Err(Error::Unexpected(_))
+        ));
+    }
+
+    #[test]
+    fn test_strip_type_prefix() {
+        assert_eq!("name", strip_type_prefix("LegacyGeneric:target=name"));
+        assert_eq!("a=b", strip_type_prefix("Domain:target=a=b"));
+        assert_eq!("key=value", strip_type_prefix("key=value"));
+        assert_eq!("=value", strip_type_prefix("=value"));
+        assert_eq!("https://host/?a=b", strip_type_prefix("https://host/?a=b"));
+    }
+
+    #[test]
+    fn test_search_target_with_equals() {
+        let name = format!("{}key=value", generate_random_string());
+        create_credential(&name, None);
+
+        let result = Search::new()
+            .expect("Error creating test search")
+            .by_target(&name);
+        delete_credential(&name);
+
+        let result = result.expect("Failed to search by target");
+        assert_eq!(
+            Some(name.as_str()),
+            result["1"].get("Target").map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn test_from_wstr_strict() {
+        let lone_surrogate = [0x61, 0xD800, 0];
+
+        assert_eq!("a\u{FFFD}", unsafe { from_wstr(lone_surrogate.as_ptr()) });
+        assert!(unsafe { from_wstr_strict(lone_surrogate.as_ptr()) }.is_err());
+        assert_eq!(
+            "name",
+            unsafe { from_wstr_strict(to_wstr("name").as_ptr()) }.expect("Valid UTF-16")
+        );
+    }
+
+    #[test]
+    fn test_strict_utf16_warnings() {
+        let name = generate_random_string();
+        let user: Vec<u16> = name.encode_utf16().chain([0xD800, 0]).collect();
+        create_credential_wide(&name, user);
+
+        let result = Search::new()
+            .expect("Error creating test search")
+            .strict_utf16(true)
+            .by_verbose("target", &name);
+        delete_credential(&name);
+
+        // The only match was skipped, which is a warning rather than NoResults.
+        let result = result.expect("Skipped credential not reported");
+        assert!(result.results.is_empty());
+        assert!(result
+            .warnings
+            .iter()
+            .any(|warning| warning.contains(&name)));
     }
 
     #[test]